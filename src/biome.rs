@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::grid::SquareType;
+
+// temperature and moisture are sampled from their own noise fields (see TerrainMap::biome_at)
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Forest,
+    Plains,
+    Desert,
+    Swamp,
+    Tundra
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeModifiers {
+    pub crop_fertility: f32, // multiplier applied to crop growth
+    pub worker_speed: f32 // multiplier applied to workers walking on it
+}
+
+impl Biome {
+    // temperature and moisture go from -1.0 to 1.0
+    pub fn classify(temperature: f64, moisture: f64) -> Biome {
+        if temperature < -0.3 {
+            Biome::Tundra
+        } else if temperature > 0.3 && moisture < -0.1 {
+            Biome::Desert
+        } else if moisture > 0.35 && temperature > -0.1 {
+            Biome::Swamp
+        } else if moisture > 0.0 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    pub fn modifiers(&self) -> BiomeModifiers {
+        match self {
+            Biome::Forest => BiomeModifiers { crop_fertility: 1.1, worker_speed: 0.85 },
+            Biome::Plains => BiomeModifiers { crop_fertility: 1.25, worker_speed: 1.0 },
+            Biome::Desert => BiomeModifiers { crop_fertility: 0.5, worker_speed: 0.9 },
+            Biome::Swamp => BiomeModifiers { crop_fertility: 0.9, worker_speed: 0.6 },
+            Biome::Tundra => BiomeModifiers { crop_fertility: 0.4, worker_speed: 0.8 }
+        }
+    }
+
+    // water and tilled soil look the same everywhere, land uses the biome palette
+    pub fn color(&self, square: SquareType) -> Color {
+        match (self, square) {
+            (_, SquareType::DeepWater) => Color::srgb(0.0, 0.2, 0.8),
+            (_, SquareType::Water) => Color::srgb(0.3, 0.5, 1.0),
+            (_, SquareType::Tilled) => Color::srgb(0.55, 0.38, 0.2),
+
+            (Biome::Plains, SquareType::Sand) => Color::srgb(1.0, 0.9, 0.6),
+            (Biome::Plains, SquareType::Grass) => Color::srgb(0.56, 0.83, 0.43),
+            (Biome::Plains, SquareType::DarkGrass) => Color::srgb(0.4, 0.65, 0.28),
+            (Biome::Plains, SquareType::Rock) => Color::srgb(0.8, 0.8, 0.8),
+
+            (Biome::Forest, SquareType::Sand) => Color::srgb(0.85, 0.8, 0.55),
+            (Biome::Forest, SquareType::Grass) => Color::srgb(0.36, 0.66, 0.3),
+            (Biome::Forest, SquareType::DarkGrass) => Color::srgb(0.2, 0.45, 0.18),
+            (Biome::Forest, SquareType::Rock) => Color::srgb(0.6, 0.62, 0.58),
+
+            (Biome::Desert, SquareType::Sand) => Color::srgb(0.98, 0.85, 0.5),
+            (Biome::Desert, SquareType::Grass) => Color::srgb(0.9, 0.78, 0.48),
+            (Biome::Desert, SquareType::DarkGrass) => Color::srgb(0.78, 0.64, 0.38),
+            (Biome::Desert, SquareType::Rock) => Color::srgb(0.75, 0.55, 0.4),
+
+            (Biome::Swamp, SquareType::Sand) => Color::srgb(0.55, 0.5, 0.35),
+            (Biome::Swamp, SquareType::Grass) => Color::srgb(0.38, 0.5, 0.3),
+            (Biome::Swamp, SquareType::DarkGrass) => Color::srgb(0.27, 0.38, 0.22),
+            (Biome::Swamp, SquareType::Rock) => Color::srgb(0.5, 0.5, 0.45),
+
+            (Biome::Tundra, SquareType::Sand) => Color::srgb(0.85, 0.85, 0.8),
+            (Biome::Tundra, SquareType::Grass) => Color::srgb(0.72, 0.8, 0.72),
+            (Biome::Tundra, SquareType::DarkGrass) => Color::srgb(0.56, 0.66, 0.6),
+            (Biome::Tundra, SquareType::Rock) => Color::srgb(0.95, 0.95, 0.97)
+        }
+    }
+}
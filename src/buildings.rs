@@ -1,7 +1,7 @@
 use std::collections::HashSet;
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
-use crate::{crop::{CropType, DirtBundle, PreparedDirtData}, gameui::{ItemSelected, UiItemSlotButton, UiSlot}, mouse::{MyWorldCoords, PointingAtUi}, player::{ItemType, PlayerInventory}, worker::{WorkerBundle, WorkerCollectable, WorkerData}};
+use crate::{economy::WageContract, gamestate::GameState, grid::{world_to_tile, SquareType, TerrainMap}, crop::{DirtBundle, PreparedDirtData, DIRT_COLOR}, gameui::{ItemSelected, UiItemSlotButton, UiSlot}, jobs::{JobBoard, JobCompleted, JobKind, Skill, WorkerSkills}, market::MarketBuilding, mouse::{MyWorldCoords, PointingAtUi}, needs::WorkerNeeds, production::ProductionBuilding, storage::{Storage, WAREHOUSE_CAPACITY}, player::{ItemType, PlayerInventory}, pathfinding::WorkerPath, worker::{worker_working, CarriedInventory, WorkerBundle, WorkerCollectable, WorkerData, WorkerState}};
 #[derive(Debug, Component)]
 pub struct HasDynamicMenu;
 
@@ -15,6 +15,26 @@ pub struct BuildingTuple {
    pub data: HashSet<((i32,i32), ItemType)>
 }
 
+// the occupied tiles and what is on them, both change together
+#[derive(SystemParam)]
+pub struct BuildingMap<'w> {
+    pub coords: ResMut<'w, BuildingCoords>,
+    pub tuples: ResMut<'w, BuildingTuple>
+}
+
+impl BuildingMap<'_> {
+    pub fn insert(&mut self, tile: (i32,i32), item: ItemType) {
+        self.coords.data.insert(tile);
+        self.tuples.data.insert((tile, item));
+    }
+
+    // the tile can be walked and built again
+    pub fn remove(&mut self, tile: (i32,i32), item: ItemType) {
+        self.coords.data.remove(&tile);
+        self.tuples.data.remove(&(tile, item));
+    }
+}
+
 #[derive(Debug, Component, PartialEq, Eq, Hash)]
 pub enum BuildingType {
     None,
@@ -29,6 +49,27 @@ pub struct HouseData {
     pub max_capacity: i32
 }
 
+// what a placed building is, daily upkeep is charged by it
+#[derive(Debug, Component, Clone, Copy)]
+pub struct PlacedBuilding(pub ItemType);
+
+// placed buildings wait for a worker to build them, see post_construction_jobs
+#[derive(Debug, Component)]
+pub struct ConstructionSite {
+    pub finished_color: Color
+}
+
+const HOUSE_COLOR: Color = Color::srgb(0.9, 0.9, 0.8);
+const MARKET_COLOR: Color = Color::srgb(0.85, 0.55, 0.2);
+const WAREHOUSE_COLOR: Color = Color::srgb(0.55, 0.4, 0.3);
+const KITCHEN_COLOR: Color = Color::srgb(0.8, 0.3, 0.25);
+const MILL_COLOR: Color = Color::srgb(0.9, 0.85, 0.5);
+
+// a see-through version of the finished color
+pub fn construction_site_color(finished: Color) -> Color {
+    finished.with_alpha(0.35)
+}
+
 #[derive(Debug, Bundle)]
 struct HouseBuildingBundle {
     tf: Transform,
@@ -36,113 +77,255 @@ struct HouseBuildingBundle {
     data: HouseData,
 }
 
+// why a click with an item selected did not place anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    NotPlaceable,
+    Occupied,
+    BlockedByTerrain(SquareType),
+    OutOfInventory
+}
+
+impl std::fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlacementError::NotPlaceable => write!(f, "can't be placed"),
+            PlacementError::Occupied => write!(f, "tile occupied"),
+            PlacementError::BlockedByTerrain(square) => write!(f, "blocked by {:?}", square),
+            PlacementError::OutOfInventory => write!(f, "out of items")
+        }
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct PlacementRejected {
+    pub item: ItemType,
+    pub tile: (i32,i32),
+    pub reason: PlacementError
+}
+
+#[derive(Debug, Event)]
+pub struct BuildingPlaced {
+    pub tile: (i32,i32)
+}
+
+struct PlacementRule {
+    item: ItemType,
+    allowed: &'static [SquareType]
+}
+
+// same terrain the workers can walk on, tilled tiles still need to be free of dirt
+const WALKABLE: &[SquareType] = &[SquareType::Sand, SquareType::Grass, SquareType::DarkGrass, SquareType::Rock, SquareType::Tilled];
+
+// terrain each placeable item can go on, items without a rule can't be placed
+const PLACEMENTRULES: [PlacementRule; 8] = [
+    PlacementRule { item: ItemType::House, allowed: &[SquareType::Grass, SquareType::DarkGrass] },
+    PlacementRule { item: ItemType::Market, allowed: &[SquareType::Grass, SquareType::DarkGrass] },
+    PlacementRule { item: ItemType::Warehouse, allowed: &[SquareType::Sand, SquareType::Grass, SquareType::DarkGrass] },
+    PlacementRule { item: ItemType::Kitchen, allowed: &[SquareType::Grass, SquareType::DarkGrass] },
+    PlacementRule { item: ItemType::Mill, allowed: &[SquareType::Grass, SquareType::DarkGrass] },
+    PlacementRule { item: ItemType::Dirt, allowed: &[SquareType::Grass, SquareType::DarkGrass] },
+    PlacementRule { item: ItemType::Coin, allowed: WALKABLE },
+    PlacementRule { item: ItemType::Worker, allowed: WALKABLE }
+];
+
+pub fn check_placement(item: ItemType, tile: (i32,i32), terrain: &TerrainMap, building_coords: &BuildingCoords) -> Result<(), PlacementError> {
+    let Some(rule) = PLACEMENTRULES.iter().find(|r| r.item == item) else { return Err(PlacementError::NotPlaceable); };
+
+    if building_coords.data.contains(&tile) { return Err(PlacementError::Occupied); }
+
+    let square = terrain.terrain_at(tile);
+    if !rule.allowed.contains(&square) { return Err(PlacementError::BlockedByTerrain(square)); }
+
+    Ok(())
+}
+
 pub struct MyBuildingPlugin;
 
 impl Plugin for MyBuildingPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(BuildingCoords { data:HashSet::new() });
         app.insert_resource(BuildingTuple { data:HashSet::new() });
+        app.add_event::<PlacementRejected>();
+        app.add_event::<BuildingPlaced>();
+
+        app.add_systems(Update, spawn_items.run_if(not(in_state(GameState::MainMenu))));
+        app.add_systems(Update, (post_construction_jobs, finish_construction.after(worker_working)).run_if(in_state(GameState::Playing)));
+    }
+}
 
-        app.add_systems(Update, spawn_items);
+fn post_construction_jobs(mut board: ResMut<JobBoard>, sites: Query<(&Transform, Entity), Added<ConstructionSite>>) {
+    for (tf, entity) in &sites {
+        board.post(JobKind::Build, entity, world_to_tile(tf.translation.truncate()), 2, Skill::Construction);
     }
 }
 
+fn finish_construction(
+    mut cmm: Commands,
+    mut completed: EventReader<JobCompleted>,
+    mut sites: Query<(&mut Sprite, &ConstructionSite)>
+) {
+    for done in completed.read().filter(|c| c.job.kind == JobKind::Build) {
+        let Ok((mut sprite, site)) = sites.get_mut(done.job.target) else { continue; };
+        sprite.color = site.finished_color;
+        cmm.entity(done.job.target).remove::<ConstructionSite>();
+    }
+}
+
+// the click that places the selected item, ignored over the ui
+#[derive(SystemParam)]
+struct PlacementClick<'w, 's> {
+    item: Res<'w, ItemSelected>,
+    pointing_at: Res<'w, PointingAtUi>,
+    world_coords: Res<'w, MyWorldCoords>,
+    input: Res<'w, ButtonInput<MouseButton>>,
+    ui_interactions: Query<'w, 's, &'static Interaction, With<Node>>
+}
+
+impl PlacementClick<'_, '_> {
+    fn just_clicked(&self) -> bool {
+        self.input.just_pressed(MouseButton::Left) && self.item.selected != ItemType::None && self.pointing_at.can_place &&
+            // clicking a button or panel (shop, time controls...) doesn't place anything behind it
+            self.ui_interactions.iter().all(|i| *i == Interaction::None)
+    }
+}
+
+#[derive(SystemParam)]
+struct PlacementEvents<'w> {
+    rejected: EventWriter<'w, PlacementRejected>,
+    placed: EventWriter<'w, BuildingPlaced>
+}
+
 fn spawn_items(
     cmm: Commands,
-    item: Res<ItemSelected>,
-    pointing_at: Res<PointingAtUi>,
+    click: PlacementClick,
     mut inv: ResMut<PlayerInventory>,
-    mut building_coords: ResMut<BuildingCoords>,
-    mut buildings_tuple: ResMut<BuildingTuple>,
-    world_coords: Res<MyWorldCoords>,
-    input: Res<ButtonInput<MouseButton>>,
+    mut building_map: BuildingMap,
+    mut terrain: ResMut<TerrainMap>,
+    mut events: PlacementEvents,
     mut ui_buttons: Query<(&mut UiSlot, Entity), With<UiItemSlotButton>>
 ) {
-    if input.just_pressed(MouseButton::Left) && item.selected != ItemType::None && pointing_at.can_place && !building_coords.data.contains(&((world_coords.0.x as i32, world_coords.0.y as i32))) {
-
-        let mut spawn_entity = |item_type: ItemType, mut cmm: Commands| {
-            let spawned = match item_type {
-                ItemType::Coin => {
-                    cmm.spawn((
-                        Sprite {
-                            color: Color::srgb(1., 0.98, 0.),
-                            custom_size: Some(Vec2 { x: 1., y: 1. }),
+    if !click.just_clicked() { return; }
+
+    let (item, world_coords) = (&click.item, click.world_coords.0);
+    let tile = world_to_tile(world_coords);
+
+    if let Err(reason) = check_placement(item.selected, tile, &terrain, &building_map.coords) {
+        events.rejected.write(PlacementRejected { item: item.selected, tile, reason });
+        return;
+    }
+
+    let mut spawn_entity = |item_type: ItemType, mut cmm: Commands| {
+        let spawned = match item_type {
+            ItemType::Coin => {
+                cmm.spawn((
+                    Sprite {
+                        color: Color::srgb(1., 0.98, 0.),
+                        custom_size: Some(Vec2 { x: 1., y: 1. }),
+                        ..default()
+                    },
+                    Transform::from_xyz(world_coords.x, world_coords.y, 2.),
+                    WorkerCollectable,
+                    ItemType::Coin
+                ));
+                (true, ItemType::Coin)
+            },
+            ItemType::House => {
+                cmm.spawn((HouseBuildingBundle {
+                    sprite: Sprite { color: construction_site_color(HOUSE_COLOR), custom_size: Some(vec2(1., 1.)), ..default() },
+                    tf: Transform::from_xyz(world_coords.x, world_coords.y, 1.),
+                    data: HouseData {
+                        building_type: BuildingType::House,
+                        assigned_workers: HashSet::new(),
+                        max_capacity: 2
+                    }
+                }, PlacedBuilding(ItemType::House), ConstructionSite { finished_color: HOUSE_COLOR }));
+                (true, ItemType::House)
+            },
+            ItemType::Market => {
+                cmm.spawn((
+                    Sprite { color: construction_site_color(MARKET_COLOR), custom_size: Some(vec2(1., 1.)), ..default() },
+                    Transform::from_xyz(world_coords.x, world_coords.y, 1.),
+                    MarketBuilding,
+                    PlacedBuilding(ItemType::Market),
+                    ConstructionSite { finished_color: MARKET_COLOR }
+                ));
+                (true, ItemType::Market)
+            },
+            ItemType::Warehouse => {
+                cmm.spawn((
+                    Sprite { color: construction_site_color(WAREHOUSE_COLOR), custom_size: Some(vec2(1., 1.)), ..default() },
+                    Transform::from_xyz(world_coords.x, world_coords.y, 1.),
+                    Storage::new(WAREHOUSE_CAPACITY),
+                    PlacedBuilding(ItemType::Warehouse),
+                    ConstructionSite { finished_color: WAREHOUSE_COLOR }
+                ));
+                (true, ItemType::Warehouse)
+            },
+            ItemType::Kitchen | ItemType::Mill => {
+                let color = if item_type == ItemType::Kitchen { KITCHEN_COLOR } else { MILL_COLOR };
+                cmm.spawn((
+                    Sprite { color: construction_site_color(color), custom_size: Some(vec2(1., 1.)), ..default() },
+                    Transform::from_xyz(world_coords.x, world_coords.y, 1.),
+                    ProductionBuilding::new(item_type),
+                    PlacedBuilding(item_type),
+                    ConstructionSite { finished_color: color }
+                ));
+                (true, item_type)
+            },
+            ItemType::Dirt=> {
+                cmm.spawn((DirtBundle {
+                    spr: Sprite { color: DIRT_COLOR, custom_size: Some(vec2(1., 1.)), ..default() },
+                    tf: Transform::from_xyz(world_coords.x, world_coords.y, 1.),
+                    data: PreparedDirtData {
+                        item_type: ItemType::Dirt,
+                        crop_type: Option::None,
+                        growth_state: 0,
+                        growth_active: false,
+                        growth_hours: 0.,
+                        growth_complete: false,
+                        planted: false,
+                        worker_assigned_bool: false,
+                        worker_assigned_entity: Entity::from_raw(0)
+                    }
+                }, HasDynamicMenu));
+                terrain.set_tile(tile, SquareType::Tilled);
+                (true, ItemType::Dirt)
+            },
+            ItemType::Worker => {
+                cmm.spawn(WorkerBundle {
+                    spr: Sprite {
+                            color: Color::srgb(1., 0.4, 0.4),
+                            custom_size: Some(Vec2 { x: 0.5, y: 0.5 }),
                             ..default()
                         },
-                        Transform::from_xyz(world_coords.0.x, world_coords.0.y, 2.),
-                        WorkerCollectable,
-                        ItemType::Coin
-                    ));
-                    (true, ItemType::Coin)
-                },
-                ItemType::House => {
-                    cmm.spawn(HouseBuildingBundle {
-                        sprite: Sprite { color:Color::srgb(0.9, 0.9, 0.8), custom_size: Some(vec2(1., 1.)), ..default() },
-                        tf: Transform::from_xyz(world_coords.0.x, world_coords.0.y, 1.),
-                        data: HouseData {
-                            building_type: BuildingType::House,
-                            assigned_workers: HashSet::new(),
-                            max_capacity: 2
-                        }
-                    });
-                    (true, ItemType::House)
-                },
-                ItemType::Dirt=> {
-                    cmm.spawn((DirtBundle {
-                        spr: Sprite { color:Color::srgb(0.7, 0.5, 0.0), custom_size: Some(vec2(1., 1.)), ..default() },
-                        tf: Transform::from_xyz(world_coords.0.x, world_coords.0.y, 1.),
-                        data: PreparedDirtData {
-                            item_type: ItemType::Dirt,
-                            crop_type: CropType::Potato, // add none as default later
-                            crop_type_selected: false, // player alredy selected one type to grow
-                            growth_state: 0,
-                            growth_active: false,
-                            growth_state_timer: Timer::from_seconds(60., TimerMode::Once),
-                            growth_complete: false,
-                            worker_assigned_bool: false,
-                            worker_assigned_entity: Entity::from_raw(0)
-                        }
-                    }, HasDynamicMenu));
-                    (true, ItemType::Dirt)
-                },
-                ItemType::Worker => {
-                    cmm.spawn(WorkerBundle {
-                        spr: Sprite {
-                                color: Color::srgb(1., 0.4, 0.4),
-                                custom_size: Some(Vec2 { x: 0.5, y: 0.5 }),
-                                ..default()
-                            },
-                        tf: Transform::from_xyz(world_coords.0.x, world_coords.0.y, 2.),
-                        data: WorkerData { 
-                            coins: 0, 
-                            worker_speed: 1.0,
-                            target_coin_entity: Option::None, 
-                            target_coin_pos: Option::None,
-                            target_coin_dir: Option::None,
-                            house_pos: (0,0),
-                            house_assigned: false,
-                            target_crop_entity: Option::None,
-                            target_crop_active: false,
-                            target_crop_pos: Option::None
-                        }
-                    });
-                    (false, ItemType::None)
-                }
-                _=> { (false, ItemType::None) }
-            };
-
-            if spawned.0 { 
-                building_coords.data.insert((world_coords.0.x as i32, world_coords.0.y as i32));
-                buildings_tuple.data.insert(((world_coords.0.x as i32, world_coords.0.y as i32), spawned.1));
+                    tf: Transform::from_xyz(world_coords.x, world_coords.y, 2.),
+                    data: WorkerData { coins: 0, worker_speed: 1.0, house: Option::None, coin: Option::None, job: Option::None, job_progress: 0. },
+                    path: WorkerPath::default(),
+                    state: WorkerState::Unemployed,
+                    needs: WorkerNeeds::default(),
+                    contract: WageContract::default(),
+                    skills: WorkerSkills::default(),
+                    carried: CarriedInventory::default()
+                });
+                (false, ItemType::None)
             }
+            _=> { (false, ItemType::None) }
         };
 
-        if let Some(stack) = inv.items.iter_mut().find(|i| (i.item != ItemType::None) && (i.total_amount >= 1) && (i.ui_entity == item.ui_entity)) {
-            stack.total_amount -= 1;
-            if let Some(mut ui_slot) = ui_buttons.iter_mut().find(|(slot,e)| (*e == stack.ui_entity) && slot.amount >= 1) {
-                ui_slot.0.amount -= 1;
-                spawn_entity(item.selected, cmm);
-            }
+        if spawned.0 { 
+            building_map.insert((world_coords.x as i32, world_coords.y as i32), spawned.1);
+            events.placed.write(BuildingPlaced { tile });
+        }
+    };
+
+    if let Some(stack) = inv.items.iter_mut().find(|i| (i.item != ItemType::None) && (i.total_amount >= 1) && (i.ui_entity == item.ui_entity)) {
+        stack.total_amount -= 1;
+        if let Some(mut ui_slot) = ui_buttons.iter_mut().find(|(slot,e)| (*e == stack.ui_entity) && slot.amount >= 1) {
+            ui_slot.0.amount -= 1;
+            spawn_entity(item.selected, cmm);
         }
+    } else {
+        events.rejected.write(PlacementRejected { item: item.selected, tile, reason: PlacementError::OutOfInventory });
     }
 }
\ No newline at end of file
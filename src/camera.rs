@@ -43,7 +43,7 @@ fn setup_camera(mut commands: Commands) {
 }
 
 fn move_camera(
-    time: Res<Time>,
+    time: Res<Time<Real>>, // the camera keeps moving while paused or sped up
     input: Res<ButtonInput<KeyCode>>,
     mut camera_position: ResMut<MainCameraActualPosition>,
     camera_query: Single<&mut Transform, With<MainCamera>>
@@ -63,7 +63,7 @@ fn move_camera(
 }
 
 fn zoom_camera(
-    time: Res<Time>,
+    time: Res<Time<Real>>,
     input: Res<ButtonInput<KeyCode>>,
     camera_query: Single<&mut Projection, With<MainCamera>>
 ) {
@@ -1,30 +1,139 @@
-use bevy::prelude::*;
+use bevy::{asset::{io::Reader, AssetLoader, LoadContext, LoadedFolder}, prelude::*};
+use serde::Deserialize;
 
-use crate::player::ItemType;
+use crate::{gamestate::GameState, grid::{world_to_tile, TerrainMap}, jobs::{JobBoard, JobCompleted, JobKind, Skill}, player::{ItemType, PlayerInventory}, worker::{worker_working, CarriedInventory, WorkerData, WorkerState}, world::{HourChanged, Season, WorldSettings}};
+
+const CROPS_FOLDER: &str = "crops";
+pub const DIRT_COLOR: Color = Color::srgb(0.7, 0.5, 0.0);
+const PLANTED_DIRT_COLOR: Color = Color::srgb(0.45, 0.3, 0.05); // darker, seeds are in the ground
 
 // idea
 // spawn the prepared dirt 
 // select the type and then the timer start
 
-#[derive(Debug, Component)]
-pub enum CropType {
-    None,
-    Potato
+// what a assets/crops/*.crop.ron file holds, sprites are asset paths with one per stage
+// items are named like the built-in ones ("Potato"), any other name is a new item of its own,
+// it needs a `price: Some(n)` to be bought (as seeds) and sold at the market
+#[derive(Debug, Deserialize)]
+struct CropFile {
+    name: String,
+    stages: i32,
+    hours_per_stage: f32,
+    sprites: Vec<String>,
+    yield_range: (i32, i32),
+    yield_item: String,
+    seed_item: String,
+    #[serde(default)]
+    price: Option<i32>,
+    seasons: Vec<Season>
+}
+
+impl CropFile {
+    fn check(&self) -> Result<(), String> {
+        if self.stages < 1 || self.sprites.len() != self.stages as usize {
+            return Err(format!("{} has {} stages but {} sprites", self.name, self.stages, self.sprites.len()));
+        }
+        if self.yield_range.0 > self.yield_range.1 {
+            return Err(format!("{} yield range {:?} is empty", self.name, self.yield_range));
+        }
+        if self.price.is_some_and(|price| price < 1) {
+            return Err(format!("{} price must be at least 1", self.name));
+        }
+        Ok(())
+    }
+}
+
+// a crop the player can grow, new crops only need a new file in the crops folder
+#[derive(Debug, Asset, TypePath)]
+pub struct CropDefinition {
+    pub name: String,
+    pub stages: i32,
+    pub hours_per_stage: f32,
+    pub sprites: Vec<Handle<Image>>,
+    pub yield_range: (i32, i32), // inclusive
+    pub yield_item: ItemType,
+    pub seed_item: ItemType, // one is used up on planting
+    pub price: Option<i32>, // market base price of the yield and seed items
+    pub seasons: Vec<Season> // when it can be planted
+}
+
+impl CropDefinition {
+    pub fn final_stage(&self) -> i32 {
+        self.stages - 1
+    }
+
+    // tended hours from planting to harvest
+    pub fn hours_to_grow(&self) -> f32 {
+        self.hours_per_stage * self.final_stage() as f32
+    }
+
+    pub fn can_plant_in(&self, season: Season) -> bool {
+        self.seasons.contains(&season)
+    }
+
+    fn harvest_amount(&self) -> i32 {
+        rand::random_range(self.yield_range.0..=self.yield_range.1)
+    }
+}
+
+#[derive(Default)]
+struct CropDefinitionLoader;
+
+impl AssetLoader for CropDefinitionLoader {
+    type Asset = CropDefinition;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), load_context: &mut LoadContext<'_>) -> Result<CropDefinition, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: CropFile = ron::de::from_bytes(&bytes)
+            .map_err(|e| format!("{} (items are quoted names, seasons must be existing Season names)", e))?;
+
+        file.check()?;
+
+        Ok(CropDefinition {
+            sprites: file.sprites.iter().map(|path| load_context.load(path)).collect(),
+            name: file.name,
+            stages: file.stages,
+            hours_per_stage: file.hours_per_stage,
+            yield_range: file.yield_range,
+            yield_item: ItemType::from_name(&file.yield_item),
+            seed_item: ItemType::from_name(&file.seed_item),
+            price: file.price,
+            seasons: file.seasons
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["crop.ron"]
+    }
+}
+
+// every crop definition sorted by name, the folder handle keeps them loaded
+#[derive(Debug, Resource)]
+pub struct CropRegistry {
+    folder: Handle<LoadedFolder>,
+    pub crops: Vec<AssetId<CropDefinition>>
 }
 
 #[derive(Debug, Component)]
 pub struct PreparedDirtData {
     pub item_type: ItemType,
-    pub crop_type: CropType,
-    pub crop_type_selected: bool,
+    pub crop_type: Option<AssetId<CropDefinition>>, // selected by the player
+    pub planted: bool, // a worker planted the selected crop
     pub growth_state: i32,
     pub growth_active: bool,
     pub growth_complete: bool,
-    pub growth_state_timer: Timer,
+    pub growth_hours: f32, // in-game hours grown into the current stage
     pub worker_assigned_bool: bool,
     pub worker_assigned_entity: Entity
 }
 
+// child of a plot showing the planted crop at its growth stage
+#[derive(Component)]
+pub struct CropSprite;
+
 #[derive(Bundle)]
 pub struct DirtBundle {
     pub tf: Transform,
@@ -36,25 +145,197 @@ pub struct MyCropPlugin;
 
 impl Plugin for MyCropPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, crop_growth_manager);
+        app.init_asset::<CropDefinition>();
+        app.init_asset_loader::<CropDefinitionLoader>();
+        app.add_systems(Startup, load_crop_definitions);
+        app.add_systems(Update, update_crop_registry);
+        app.add_systems(Update, crop_growth_manager.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, (spawn_crop_sprites, update_crop_sprites).chain().after(crop_growth_manager));
+        // harvested goods must be carried before the worker is idle again
+        app.add_systems(Update, (post_crop_jobs, crop_job_results.after(worker_working), crop_worker_assignment).run_if(in_state(GameState::Playing)));
         app.add_systems(Update, log_crop_data);
     }
 }
 
-// iter all the crops available and check if they area active
+fn load_crop_definitions(mut cmm: Commands, asset_server: Res<AssetServer>) {
+    cmm.insert_resource(CropRegistry { folder: asset_server.load_folder(CROPS_FOLDER), crops: vec![] });
+}
+
+fn update_crop_registry(
+    mut events: EventReader<AssetEvent<LoadedFolder>>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<CropDefinition>>,
+    mut registry: ResMut<CropRegistry>
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else { continue; };
+        if *id != registry.folder.id() { continue; }
+        let Some(folder) = folders.get(*id) else { continue; };
+
+        let mut crops: Vec<(AssetId<CropDefinition>, &CropDefinition)> = folder.handles.iter()
+            .filter_map(|handle| handle.clone().try_typed::<CropDefinition>().ok())
+            .filter_map(|handle| definitions.get(&handle).map(|def| (handle.id(), def)))
+            .collect();
+        crops.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+
+        for (_, def) in &crops { info!("[crops] loaded {}, grows in {} hours", def.name, def.hours_to_grow()); }
+        registry.crops = crops.into_iter().map(|c| c.0).collect();
+    }
+}
+
+// every in-game hour the tended crops grow, so the game speed and pausing affect them like the clock
+// the biome fertility speeds up or slows down the growth
 fn crop_growth_manager(
-    time: Res<Time>,
-    mut crops: Query<&mut PreparedDirtData, With<PreparedDirtData>>
+    mut hour_changed: EventReader<HourChanged>,
+    terrain: Res<TerrainMap>,
+    definitions: Res<Assets<CropDefinition>>,
+    mut crops: Query<(&mut PreparedDirtData, &Transform), With<PreparedDirtData>>
+) {
+    for _ in hour_changed.read() {
+        for (mut crop, tf) in &mut crops {
+            if !crop.growth_active || !crop.planted || crop.growth_complete { continue; }
+            let Some(def) = crop.crop_type.and_then(|id| definitions.get(id)) else { continue; };
+
+            crop.growth_hours += terrain.biome_at(world_to_tile(tf.translation.truncate())).modifiers().crop_fertility;
+            if crop.growth_hours >= def.hours_per_stage {
+                crop.growth_state += 1;
+                crop.growth_hours = 0.;
+                if crop.growth_state >= def.final_stage() {
+                    crop.growth_complete = true;
+                    crop.growth_active = false;
+                }
+            }
+        }
+    }
+}
+
+fn spawn_crop_sprites(mut cmm: Commands, plots: Query<Entity, Added<PreparedDirtData>>) {
+    for plot in &plots {
+        cmm.entity(plot).with_child((
+            CropSprite,
+            Sprite { custom_size: Some(vec2(1., 1.)), ..default() },
+            Transform::from_xyz(0., 0., 0.1),
+            Visibility::Hidden
+        ));
+    }
+}
+
+// plots whose crop changed, the crop sprite is a child with its own Sprite
+type ChangedPlotFilter = (Changed<PreparedDirtData>, Without<CropSprite>);
+
+// bare plots are plain dirt, planted ones get darker soil and the sprite of their stage
+fn update_crop_sprites(
+    definitions: Res<Assets<CropDefinition>>,
+    mut plots: Query<(&PreparedDirtData, &mut Sprite, &Children), ChangedPlotFilter>,
+    mut crop_sprites: Query<(&mut Sprite, &mut Visibility), With<CropSprite>>
+) {
+    for (crop, mut plot_sprite, children) in &mut plots {
+        let stage_sprite = crop.crop_type.and_then(|id| definitions.get(id))
+            .filter(|_| crop.planted)
+            .and_then(|def| def.sprites.get(crop.growth_state.clamp(0, def.final_stage()) as usize));
+
+        let soil = if stage_sprite.is_some() { PLANTED_DIRT_COLOR } else { DIRT_COLOR };
+        if plot_sprite.color != soil { plot_sprite.color = soil; }
+
+        let Some(child) = children.iter().find(|child| crop_sprites.contains(*child)) else { continue; };
+        let Ok((mut sprite, mut visibility)) = crop_sprites.get_mut(child) else { continue; };
+
+        match stage_sprite {
+            Some(image) => {
+                if sprite.image != *image { sprite.image = image.clone(); }
+                visibility.set_if_neq(Visibility::Inherited);
+            },
+            None => { visibility.set_if_neq(Visibility::Hidden); }
+        }
+    }
+}
+
+// selected crops need planting when there are seeds and the season allows it,
+// planted crops need tending and ready crops need harvesting
+fn post_crop_jobs(
+    day: Res<WorldSettings>,
+    inventory: Res<PlayerInventory>,
+    definitions: Res<Assets<CropDefinition>>,
+    mut board: ResMut<JobBoard>,
+    crops: Query<(&PreparedDirtData, &Transform, Entity)>
+) {
+    for (crop, tf, entity) in &crops {
+        let tile = world_to_tile(tf.translation.truncate());
+
+        if !crop.planted {
+            let plantable = crop.crop_type.and_then(|id| definitions.get(id))
+                .is_some_and(|def| def.can_plant_in(day.season) && inventory.count(def.seed_item) > 0);
+
+            if plantable { board.post(JobKind::Plant, entity, tile, 2, Skill::Farming); }
+            else { board.remove(JobKind::Plant, entity); }
+        } else if !crop.growth_complete {
+            board.post(JobKind::Tend, entity, tile, 1, Skill::Farming);
+        } else {
+            board.remove(JobKind::Tend, entity);
+            board.post(JobKind::Harvest, entity, tile, 3, Skill::Farming);
+        }
+    }
+}
+
+// planting uses up a seed, the harvest goes to the worker hands
+// and the plot is bare again until the player picks a new crop
+fn crop_job_results(
+    definitions: Res<Assets<CropDefinition>>,
+    mut inventory: ResMut<PlayerInventory>,
+    mut completed: EventReader<JobCompleted>,
+    mut workers: Query<&mut CarriedInventory>,
+    mut crops: Query<&mut PreparedDirtData>
+) {
+    for done in completed.read() {
+        let Ok(mut crop) = crops.get_mut(done.job.target) else { continue; };
+        let Some(def) = crop.crop_type.and_then(|id| definitions.get(id)) else { continue; };
+
+        match done.job.kind {
+            JobKind::Plant if inventory.take(def.seed_item, 1) => { crop.planted = true; },
+            JobKind::Harvest => {
+                if let Ok(mut carried) = workers.get_mut(done.worker) {
+                    carried.add(def.yield_item, def.harvest_amount());
+                }
+
+                crop.crop_type = Option::None;
+                crop.planted = false;
+                crop.growth_state = 0;
+                crop.growth_active = false;
+                crop.growth_complete = false;
+                crop.growth_hours = 0.;
+                crop.worker_assigned_bool = false;
+                crop.worker_assigned_entity = Entity::from_raw(0);
+            },
+            _ => {}
+        }
+    }
+}
+
+// the crop follows the board: assigned to whoever claimed one of its jobs, growing while a worker tends it
+fn crop_worker_assignment(
+    board: Res<JobBoard>,
+    workers: Query<(&WorkerData, &WorkerState)>,
+    mut crops: Query<(&mut PreparedDirtData, Entity)>
 ) {
-    for mut crop in &mut crops {
-        if crop.growth_active && crop.crop_type_selected {
-            crop.growth_state_timer.tick(time.delta());
-            if crop.growth_state_timer.just_finished() { crop.growth_state += 1; }
+    let tended: Vec<Entity> = workers.iter()
+        .filter(|(data, state)| **state == WorkerState::Working && data.job.is_some_and(|j| j.kind == JobKind::Tend))
+        .filter_map(|(data, _)| data.job.map(|j| j.target))
+        .collect();
+
+    for (mut crop, entity) in &mut crops {
+        let claimed = [JobKind::Plant, JobKind::Tend, JobKind::Harvest].iter().find_map(|kind| board.claimed_job(*kind, entity)).and_then(|j| j.claimed_by);
+
+        let (assigned, assigned_entity, growth_active) = (claimed.is_some(), claimed.unwrap_or(Entity::from_raw(0)), tended.contains(&entity));
+
+        // only touch the crop when something changed so Changed<PreparedDirtData> stays meaningful
+        if crop.worker_assigned_bool != assigned || crop.worker_assigned_entity != assigned_entity || crop.growth_active != growth_active {
+            crop.worker_assigned_bool = assigned;
+            crop.worker_assigned_entity = assigned_entity;
+            crop.growth_active = growth_active;
         }
     }
 }
 
-// when harvested reset the assigned worker and crop_type_selected to false
 
 fn log_crop_data(
     input: Res<ButtonInput<KeyCode>>,
@@ -65,4 +346,50 @@ fn log_crop_data(
             println!("crop: {:?}", crop);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shipped_crop_files() -> Vec<CropFile> {
+        let folder = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(CROPS_FOLDER);
+        std::fs::read_dir(folder).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().ends_with(".crop.ron"))
+            .map(|path| ron::de::from_bytes(&std::fs::read(&path).unwrap()).unwrap_or_else(|e| panic!("{}: {}", path.display(), e)))
+            .collect()
+    }
+
+    #[test]
+    fn shipped_crop_files_are_valid() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+
+        for file in shipped_crop_files() {
+            assert_eq!(file.check(), Ok(()));
+            for sprite in &file.sprites {
+                assert!(assets.join(sprite).is_file(), "{} sprite {} is missing", file.name, sprite);
+            }
+        }
+    }
+
+    // the mill has nothing to grind without it
+    #[test]
+    fn wheat_can_be_grown() {
+        let wheat = shipped_crop_files().into_iter().find(|file| ItemType::from_name(&file.yield_item) == ItemType::Wheat).expect("no crop yields wheat");
+        assert_eq!(ItemType::from_name(&wheat.seed_item), ItemType::Wheat);
+        assert!(!wheat.seasons.is_empty());
+    }
+
+    #[test]
+    fn unknown_item_names_are_new_items() {
+        let carrot = shipped_crop_files().into_iter().find(|file| file.name == "Carrot").expect("no carrot crop");
+        let item = ItemType::from_name(&carrot.yield_item);
+
+        assert!(matches!(item, ItemType::Produce(_)));
+        assert_eq!(item, ItemType::from_name("Carrot"));
+        assert_eq!(format!("{:?}", item), "Carrot");
+        // without a price it could never be bought or sold
+        assert!(carrot.price.is_some());
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{buildings::HouseData, gamestate::GameState, jobs::JobBoard, pathfinding::WorkerPath, world::{DayStarted, HourChanged}, worker::{transition, WorkerData, WorkerState, WorkerStateChanged}};
+
+const STARTING_TREASURY: i32 = 100;
+pub const DAILY_WAGE: i32 = 5;
+const MAX_GRIEVANCES: i32 = 3; // unpaid days before a worker quits
+const PAYDAY_HOUR: u32 = 18;
+const LEDGER_DAYS: usize = 7; // closed days kept in the ledger
+
+// town money, wages are paid from here
+#[derive(Debug, Resource)]
+pub struct Treasury {
+    pub coins: i32
+}
+
+impl Treasury {
+    // returns false without touching the coins when there is not enough money
+    pub fn spend(&mut self, amount: i32, category: LedgerCategory, changed: &mut EventWriter<TreasuryChanged>) -> bool {
+        if self.coins < amount { return false; }
+        self.coins -= amount;
+        changed.write(TreasuryChanged { coins: self.coins, delta: -amount, category });
+        true
+    }
+
+    pub fn earn(&mut self, amount: i32, category: LedgerCategory, changed: &mut EventWriter<TreasuryChanged>) {
+        self.coins += amount;
+        changed.write(TreasuryChanged { coins: self.coins, delta: amount, category });
+    }
+}
+
+// the treasury with its change events, for systems that move coins
+#[derive(SystemParam)]
+pub struct TreasuryAccess<'w> {
+    pub treasury: ResMut<'w, Treasury>,
+    pub changed: EventWriter<'w, TreasuryChanged>
+}
+
+impl TreasuryAccess<'_> {
+    pub fn spend(&mut self, amount: i32, category: LedgerCategory) -> bool {
+        self.treasury.spend(amount, category, &mut self.changed)
+    }
+
+    pub fn earn(&mut self, amount: i32, category: LedgerCategory) {
+        self.treasury.earn(amount, category, &mut self.changed);
+    }
+}
+
+// what the treasury coins were moved for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LedgerCategory {
+    Wages,
+    Upkeep,
+    Rent,
+    Sales,
+    Purchases
+}
+
+// income and expenses of one day by category, expenses are positive
+#[derive(Debug, Default, Clone)]
+pub struct LedgerDay {
+    pub income: HashMap<LedgerCategory, i32>,
+    pub expenses: HashMap<LedgerCategory, i32>
+}
+
+impl LedgerDay {
+    fn record(&mut self, category: LedgerCategory, delta: i32) {
+        if delta >= 0 {
+            *self.income.entry(category).or_insert(0) += delta;
+        } else {
+            *self.expenses.entry(category).or_insert(0) -= delta;
+        }
+    }
+
+    pub fn total_income(&self) -> i32 {
+        self.income.values().sum()
+    }
+
+    pub fn total_expenses(&self) -> i32 {
+        self.expenses.values().sum()
+    }
+}
+
+// every treasury change of today, closed days are kept newest first
+#[derive(Debug, Resource, Default)]
+pub struct Ledger {
+    pub today: LedgerDay,
+    pub history: VecDeque<LedgerDay>
+}
+
+// every hired worker has one, unpaid days add grievances and paid days remove them
+#[derive(Debug, Component)]
+pub struct WageContract {
+    pub daily_wage: i32,
+    pub grievances: i32
+}
+
+impl Default for WageContract {
+    fn default() -> Self {
+        WageContract { daily_wage: DAILY_WAGE, grievances: 0 }
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct TreasuryChanged {
+    pub coins: i32,
+    pub delta: i32,
+    pub category: LedgerCategory
+}
+
+#[derive(Debug, Event)]
+pub struct WorkerPaid {
+    pub worker: Entity,
+    pub amount: i32
+}
+
+#[derive(Debug, Event)]
+pub struct WageUnpaid {
+    pub worker: Entity,
+    pub grievances: i32
+}
+
+#[derive(Debug, Event)]
+pub struct WorkerQuit {
+    pub worker: Entity
+}
+
+#[derive(Debug, Event)]
+pub struct PayrollCompleted {
+    pub paid: i32,
+    pub unpaid: i32,
+    pub total: i32
+}
+
+// sent at midnight with the day that just ended
+#[derive(Debug, Event)]
+pub struct LedgerClosed {
+    pub income: i32,
+    pub expenses: i32
+}
+
+pub struct MyEconomyPlugin;
+
+impl Plugin for MyEconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Treasury { coins: STARTING_TREASURY });
+        app.init_resource::<Ledger>();
+        app.add_event::<TreasuryChanged>();
+        app.add_event::<WorkerPaid>();
+        app.add_event::<WageUnpaid>();
+        app.add_event::<WorkerQuit>();
+        app.add_event::<PayrollCompleted>();
+        app.add_event::<LedgerClosed>();
+
+        app.add_systems(Update, (daily_payroll, release_quitting_workers).chain().run_if(in_state(GameState::Playing)));
+        // the shop also moves coins while paused
+        app.add_systems(Update, (close_ledger, record_ledger).chain());
+        app.add_systems(Update, (log_wage_events, log_ledger));
+    }
+}
+
+fn is_hired(state: &WorkerState) -> bool {
+    !matches!(state, WorkerState::Unemployed | WorkerState::SeekingPay)
+}
+
+fn daily_payroll(
+    mut hour_changed: EventReader<HourChanged>,
+    mut funds: TreasuryAccess,
+    mut paid_events: EventWriter<WorkerPaid>,
+    mut unpaid_events: EventWriter<WageUnpaid>,
+    mut quit_events: EventWriter<WorkerQuit>,
+    mut payroll_events: EventWriter<PayrollCompleted>,
+    mut workers: Query<(&mut WorkerData, &mut WageContract, &WorkerState, Entity)>
+) {
+    if !hour_changed.read().any(|h| h.hour == PAYDAY_HOUR) { return; }
+
+    let (mut paid, mut unpaid, mut total) = (0, 0, 0);
+
+    for (mut worker_data, mut contract, state, worker_entity) in &mut workers {
+        if !is_hired(state) { continue; }
+
+        if funds.spend(contract.daily_wage, LedgerCategory::Wages) {
+            worker_data.coins += contract.daily_wage;
+            contract.grievances = (contract.grievances - 1).max(0);
+            paid_events.write(WorkerPaid { worker: worker_entity, amount: contract.daily_wage });
+            paid += 1;
+            total += contract.daily_wage;
+        } else {
+            contract.grievances += 1;
+            unpaid_events.write(WageUnpaid { worker: worker_entity, grievances: contract.grievances });
+            unpaid += 1;
+
+            if contract.grievances >= MAX_GRIEVANCES { quit_events.write(WorkerQuit { worker: worker_entity }); }
+        }
+    }
+
+    payroll_events.write(PayrollCompleted { paid, unpaid, total });
+}
+
+// a worker that quits gives back the house slot and the job and goes back to look for coins
+fn release_quitting_workers(
+    mut quit_events: EventReader<WorkerQuit>,
+    mut changed: EventWriter<WorkerStateChanged>,
+    mut board: ResMut<JobBoard>,
+    mut houses: Query<&mut HouseData>,
+    mut workers: Query<(&mut WorkerData, &mut WageContract, &mut WorkerPath, &mut WorkerState)>
+) {
+    for quit in quit_events.read() {
+        let Ok((mut worker_data, mut contract, mut path, mut state)) = workers.get_mut(quit.worker) else { continue; };
+
+        if let Some(mut house) = worker_data.house.and_then(|h| houses.get_mut(h.entity).ok()) {
+            house.assigned_workers.remove(&quit.worker);
+        }
+
+        board.release_worker(quit.worker);
+
+        worker_data.house = Option::None;
+        worker_data.job = Option::None;
+        worker_data.job_progress = 0.;
+        contract.grievances = 0;
+        path.clear();
+        transition(quit.worker, &mut state, WorkerState::Unemployed, &mut changed);
+    }
+}
+
+fn close_ledger(mut day_started: EventReader<DayStarted>, mut ledger: ResMut<Ledger>, mut closed: EventWriter<LedgerClosed>) {
+    for _ in day_started.read() {
+        let day = std::mem::take(&mut ledger.today);
+        closed.write(LedgerClosed { income: day.total_income(), expenses: day.total_expenses() });
+
+        ledger.history.push_front(day);
+        ledger.history.truncate(LEDGER_DAYS);
+    }
+}
+
+fn record_ledger(mut treasury_changed: EventReader<TreasuryChanged>, mut ledger: ResMut<Ledger>) {
+    for event in treasury_changed.read() {
+        ledger.today.record(event.category, event.delta);
+    }
+}
+
+fn log_ledger(input: Res<ButtonInput<KeyCode>>, ledger: Res<Ledger>) {
+    if input.just_pressed(KeyCode::KeyL) {
+        for (days_ago, day) in std::iter::once(&ledger.today).chain(ledger.history.iter()).enumerate() {
+            info!("[ledger] {} days ago: +{} {:?} -{} {:?}", days_ago, day.total_income(), day.income, day.total_expenses(), day.expenses);
+        }
+    }
+}
+
+fn log_wage_events(mut paid: EventReader<WorkerPaid>, mut unpaid: EventReader<WageUnpaid>, mut treasury_changed: EventReader<TreasuryChanged>) {
+    for event in paid.read() { debug!("[payroll] {} paid {}", event.worker, event.amount); }
+    for event in unpaid.read() { debug!("[payroll] {} unpaid, grievances {}", event.worker, event.grievances); }
+    for event in treasury_changed.read() { debug!("[treasury] {:?} {:+} -> {}", event.category, event.delta, event.coins); }
+}
@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+const SPEEDS: [f32; 4] = [1., 2., 4., 8.];
+const SPEED_KEYS: [KeyCode; 4] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4];
+const TIME_BUTTON_COLOR: Color = Color::srgb(0.26, 0.26, 0.26);
+const TIME_BUTTON_ACTIVE_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+// simulation systems only run while Playing
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused
+}
+
+// multiplier of the virtual time, the day clock, crops and worker movement all follow it
+#[derive(Debug, Resource)]
+pub struct SimulationSpeed(pub f32);
+
+// a top-bar button, the ui spawns one for pause and one per speed
+#[derive(Debug, Component, Clone, Copy, PartialEq)]
+pub enum TimeControlButton {
+    Pause,
+    Speed(f32)
+}
+
+impl TimeControlButton {
+    pub fn all() -> impl Iterator<Item = TimeControlButton> {
+        std::iter::once(TimeControlButton::Pause).chain(SPEEDS.iter().map(|s| TimeControlButton::Speed(*s)))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            TimeControlButton::Pause => "||".to_string(),
+            TimeControlButton::Speed(speed) => format!("{}x", speed)
+        }
+    }
+}
+
+#[derive(Component)]
+struct MainMenuUi;
+
+pub struct MyGameStatePlugin;
+
+impl Plugin for MyGameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>();
+        app.insert_resource(SimulationSpeed(1.));
+
+        app.add_systems(OnEnter(GameState::MainMenu), (spawn_main_menu, pause_time));
+        app.add_systems(OnExit(GameState::MainMenu), (despawn_main_menu, unpause_time));
+        app.add_systems(OnEnter(GameState::Paused), pause_time);
+        app.add_systems(OnExit(GameState::Paused), unpause_time);
+
+        app.add_systems(Update, main_menu_actions.run_if(in_state(GameState::MainMenu)));
+        app.add_systems(Update, (time_control_hotkeys, time_control_buttons, highlight_time_control).run_if(not(in_state(GameState::MainMenu))));
+        app.add_systems(Update, apply_simulation_speed.run_if(resource_changed::<SimulationSpeed>));
+    }
+}
+
+fn spawn_main_menu(mut cmm: Commands) {
+    cmm.spawn((
+        Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            display: Display::Flex,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(16.),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.9)),
+        GlobalZIndex(10),
+        MainMenuUi
+    )).with_children(|menu| {
+        menu.spawn((Text::new("simsim"), TextFont { font_size: 40., ..default() }));
+        menu.spawn((
+            Node { padding: UiRect::axes(Val::Px(24.), Val::Px(8.)), ..default() },
+            BackgroundColor(TIME_BUTTON_COLOR),
+            Button
+        )).with_child((Text::new("Play"), TextFont { font_size: 20., ..default() }));
+        menu.spawn((Text::new("enter to play, space to pause, 1-4 to change the speed"), TextFont { font_size: 12., ..default() }));
+    });
+}
+
+fn despawn_main_menu(mut cmm: Commands, menu: Query<Entity, With<MainMenuUi>>) {
+    for entity in &menu { cmm.entity(entity).despawn(); }
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn apply_simulation_speed(speed: Res<SimulationSpeed>, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(speed.0);
+}
+
+fn main_menu_actions(
+    input: Res<ButtonInput<KeyCode>>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    menu: Query<&Children, With<MainMenuUi>>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    let play_pressed = menu.iter().flat_map(|children| children.iter())
+        .any(|child| buttons.get(child).is_ok_and(|i| *i == Interaction::Pressed));
+
+    if play_pressed || input.just_pressed(KeyCode::Enter) {
+        next_state.set(GameState::Playing);
+    }
+}
+
+// pausing toggles, picking a speed also resumes the game
+fn apply_time_control(control: TimeControlButton, state: GameState, speed: &mut SimulationSpeed, next_state: &mut NextState<GameState>) {
+    match control {
+        TimeControlButton::Pause => {
+            next_state.set(if state == GameState::Paused { GameState::Playing } else { GameState::Paused });
+        },
+        TimeControlButton::Speed(multiplier) => {
+            speed.0 = multiplier;
+            next_state.set(GameState::Playing);
+        }
+    }
+}
+
+fn time_control_hotkeys(
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut next_state: ResMut<NextState<GameState>>
+) {
+    if input.just_pressed(KeyCode::Space) {
+        apply_time_control(TimeControlButton::Pause, *state.get(), &mut speed, &mut next_state);
+    }
+
+    if let Some(index) = SPEED_KEYS.iter().position(|key| input.just_pressed(*key)) {
+        apply_time_control(TimeControlButton::Speed(SPEEDS[index]), *state.get(), &mut speed, &mut next_state);
+    }
+}
+
+fn time_control_buttons(
+    state: Res<State<GameState>>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut next_state: ResMut<NextState<GameState>>,
+    buttons: Query<(&Interaction, &TimeControlButton), Changed<Interaction>>
+) {
+    for (interaction, control) in &buttons {
+        if *interaction == Interaction::Pressed {
+            apply_time_control(*control, *state.get(), &mut speed, &mut next_state);
+        }
+    }
+}
+
+fn highlight_time_control(
+    state: Res<State<GameState>>,
+    speed: Res<SimulationSpeed>,
+    mut buttons: Query<(&TimeControlButton, &mut BackgroundColor)>
+) {
+    for (control, mut bgc) in &mut buttons {
+        let active = match control {
+            TimeControlButton::Pause => *state.get() == GameState::Paused,
+            TimeControlButton::Speed(multiplier) => *state.get() == GameState::Playing && *multiplier == speed.0
+        };
+        bgc.0 = if active { TIME_BUTTON_ACTIVE_COLOR } else { TIME_BUTTON_COLOR };
+    }
+}
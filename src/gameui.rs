@@ -1,8 +1,9 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
-use crate::{buildings::{BuildingCoords, BuildingTuple, HasDynamicMenu}, crop::PreparedDirtData, mouse::MyWorldCoords, player::{ItemType, PlayerInventory, INVENTORYSIZE}, world::WorldSettings};
+use crate::{buildings::{BuildingTuple, HasDynamicMenu, PlacementRejected}, crop::{CropDefinition, CropRegistry, PreparedDirtData}, economy::{LedgerClosed, PayrollCompleted, Treasury, TreasuryAccess, WorkerQuit}, gamestate::TimeControlButton, storage::TownStock, upkeep::{BuildingDisrepair, RentCollected, UpkeepUnpaid}, market::{buy_into_inventory, GoodsBought, GoodsSold, MarketPrices, PurchaseRejected}, mouse::MyWorldCoords, player::{ItemType, PlayerInventory, INVENTORYSIZE}, world::WorldSettings};
 
 const RGBINVSLOT: (f32,f32,f32) = (0.4,0.5,0.4);
+const MAX_CROP_BUTTONS: usize = 5; // the dynamic menu has 6 buttons, the last one closes it
 
 #[derive(Debug, Component, PartialEq, Eq)]
 enum DynamicButtonId {
@@ -14,8 +15,21 @@ enum DynamicButtonId {
     ButtonSix
 }
 
+impl DynamicButtonId {
+    fn index(&self) -> usize {
+        match self {
+            DynamicButtonId::ButtonOne => 0,
+            DynamicButtonId::ButtonTwo => 1,
+            DynamicButtonId::ButtonThree => 2,
+            DynamicButtonId::ButtonFour => 3,
+            DynamicButtonId::ButtonFive => 4,
+            DynamicButtonId::ButtonSix => 5
+        }
+    }
+}
+
 enum DynamicOption {
-    Potato,
+    Crop, // one button per loaded crop definition
     Close
 }
 
@@ -25,7 +39,7 @@ struct DynamicMenuOptions {
 }
 
 const DYNAMICMENUOPTIONS: [DynamicMenuOptions; 1] = [
-    DynamicMenuOptions { building: ItemType::Dirt, action: &[DynamicOption::Potato, DynamicOption::Close] }
+    DynamicMenuOptions { building: ItemType::Dirt, action: &[DynamicOption::Crop, DynamicOption::Close] }
 ];
 
 #[derive(Component)]
@@ -34,6 +48,26 @@ pub struct UiItemSlotButton;
 #[derive(Component)]
 pub struct UiWorldTime;
 
+#[derive(Component)]
+pub struct UiMessage;
+
+#[derive(Component)]
+pub struct UiTreasury;
+
+// town-wide stock shown next to the inventory
+#[derive(Component)]
+struct UiTownStock;
+
+#[derive(Component)]
+struct UiShopButton;
+
+#[derive(Component)]
+struct UiShopPanel;
+
+// a row of the shop, clicking it buys one (ten with shift)
+#[derive(Component)]
+struct UiShopItem(ItemType);
+
 #[derive(Resource, Debug)]
 pub struct ItemSelected {
     pub selected: ItemType,
@@ -74,10 +108,12 @@ impl Plugin for MyGameUiPlugin {
         app.insert_resource(DynamicUi { selected: ItemType::None, world_entity: Entity::from_raw(0), parent_ui_entity: Entity::from_raw(0), actual_ui_entity: Entity::from_raw(0), button_count: 0, open: false });
         
         app.add_systems(Startup, ui_setup);     
-        app.add_systems(Update, (ui_slot_interactions, ui_load_items, ui_reset_slot, reset_player_item_selected));
+        app.add_systems(Update, (ui_slot_interactions, ui_load_items, ui_sync_slot_amount, ui_reset_slot, reset_player_item_selected));
         app.add_systems(Update, (highlight_slot_selected, reset_selected_item));
-        app.add_systems(Update, (ui_slot_text, ui_world_time_text));
+        app.add_systems(Update, (ui_slot_text, ui_world_time_text, ui_message_text, ui_treasury_text));
         app.add_systems(Update, (dyn_ui_selection, display_dyn_ui_selected, dynamic_menu_actions));
+        app.add_systems(Update, (toggle_shop, shop_buy_items, ui_shop_rows, ui_shop_prices));
+        app.add_systems(Update, ui_town_stock_text);
     }
 }
 
@@ -115,9 +151,39 @@ fn ui_setup(mut commands: Commands, mut dyn_ui: ResMut<DynamicUi>) {
                         height: Val::Px(24.), 
                         display: Display::Flex,
                         flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(2.),
                         ..default()
                     }, BackgroundColor(Color::srgb(rgb_topbar.0,rgb_topbar.1,rgb_topbar.2))
-                ));
+                )).with_children(|buttons| {
+                    // TIME CONTROLS
+                    for control in TimeControlButton::all() {
+                        buttons.spawn((
+                            Node {
+                                width: Val::Px(28.),
+                                height: Val::Px(24.),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.26,0.26,0.26)),
+                            Button,
+                            control
+                        )).with_child((Text::new(control.label()), TextFont { font_size: 12., ..default() }));
+                    }
+                    // SHOP
+                    buttons.spawn((
+                        Node {
+                            height: Val::Px(24.),
+                            padding: UiRect::horizontal(Val::Px(6.)),
+                            margin: UiRect::left(Val::Px(8.)),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.26,0.26,0.26)),
+                        Button,
+                        UiShopButton
+                    )).with_child((Text::new("Shop"), TextFont { font_size: 12., ..default() }));
+                });
 
                 cc.spawn(
                     Node {
@@ -131,14 +197,37 @@ fn ui_setup(mut commands: Commands, mut dyn_ui: ResMut<DynamicUi>) {
                     // WORLD TIMER
                     ccc.spawn((
                         Node {
-                            width: Val::Px(98.),
                             height: Val::Px(24.),
+                            padding: UiRect::horizontal(Val::Px(6.)),
                             display: Display::Flex,
                             flex_direction: FlexDirection::RowReverse,
                             ..default()
                         }, BackgroundColor(Color::srgb(0.4,0.6,0.6)),
+                        TextFont { font_size: 14., ..default() },
                         UiWorldTime,
-                        Text2d::new("00:00")
+                        Text::new("00:00")
+                    ));
+                    // TREASURY
+                    ccc.spawn((
+                        Node {
+                            height: Val::Px(24.),
+                            margin: UiRect::right(Val::Px(8.)),
+                            ..default()
+                        },
+                        TextFont { font_size: 14., ..default() },
+                        UiTreasury,
+                        Text::new("")
+                    ));
+                    // MESSAGES
+                    ccc.spawn((
+                        Node {
+                            height: Val::Px(24.),
+                            margin: UiRect::right(Val::Px(8.)),
+                            ..default()
+                        },
+                        TextFont { font_size: 14., ..default() },
+                        UiMessage,
+                        Text::new("")
                     ));
                 });
             });
@@ -167,7 +256,7 @@ fn ui_setup(mut commands: Commands, mut dyn_ui: ResMut<DynamicUi>) {
                 inv.spawn((
                     Node {
                         width: Val::Px(60.),
-                        height: Val::Px(300.),
+                        height: Val::Px(400.),
                         display: Display::Flex,
                         row_gap: Val::Px(inventory_row_gap),
                         flex_direction: FlexDirection::Column,
@@ -181,6 +270,21 @@ fn ui_setup(mut commands: Commands, mut dyn_ui: ResMut<DynamicUi>) {
                     slots.spawn(build_custom_button(ItemType::None,0,RGBINVSLOT));
                 }
                 });
+                // TOWN STOCK
+                inv.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(64.),
+                        width: Val::Px(110.),
+                        display: Display::None,
+                        padding: UiRect::all(Val::Px(4.)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.3,0.3,0.3,0.8)),
+                    TextFont { font_size: 11., ..default() },
+                    Text::new(""),
+                    UiTownStock
+                ));
             });
 
             middle.spawn(Node {
@@ -224,6 +328,7 @@ fn ui_setup(mut commands: Commands, mut dyn_ui: ResMut<DynamicUi>) {
         });
     });
 
+    spawn_shop_panel(&mut commands);
 }
 
 fn build_custom_button(item: ItemType, amount: i32,rgb: (f32,f32,f32)) -> impl Bundle  {
@@ -265,6 +370,16 @@ fn ui_load_items(mut inventory_slots: Query<(&mut UiSlot, Entity), With<UiItemSl
     }
 }
 
+// stacks can also change outside the ui (workers eating, harvests...)
+fn ui_sync_slot_amount(mut inventory_slots: Query<(&mut UiSlot, Entity), With<UiItemSlotButton>>, player_inventory: Res<PlayerInventory>) {
+    for (mut slot, entity) in &mut inventory_slots {
+        if slot.item == ItemType::None { continue; }
+
+        let amount = player_inventory.items.iter().find(|i| i.assigned && i.ui_entity == entity).map_or(0, |i| i.total_amount);
+        if slot.amount != amount { slot.amount = amount; }
+    }
+}
+
 // when inventory stack gets to 0 gets reset to Item::None 
 fn ui_reset_slot(mut inventory_slots: Query<&mut UiSlot, With<UiItemSlotButton>>) {
     if let Some(mut ui_button) = inventory_slots.iter_mut().find(|uib|(uib.amount < 1) && (uib.item != ItemType::None)) {
@@ -277,13 +392,82 @@ fn ui_slot_text(mut inventory_slots: Query<(&UiSlot, &mut Text), (With<UiItemSlo
     for (slot, mut text) in &mut inventory_slots { text.0 = slot.amount.to_string(); }
 }
 
-fn ui_world_time_text(mut ui_time_text: Query<&mut Text2d, With<UiWorldTime>>,world_settings: Res<WorldSettings>) {
+fn ui_world_time_text(mut ui_time_text: Query<&mut Text, With<UiWorldTime>>,world_settings: Res<WorldSettings>) {
     if let Some(mut text) = ui_time_text.iter_mut().next() {
-        let world_time = format!("{:?}:00 {:?}", world_settings.actual_hour as i32, world_settings.meridiem);
+        let world_time = format!("{:?} {}, Year {}  {:02}:00", world_settings.season, world_settings.day, world_settings.year, world_settings.hour);
         text.0 = world_time.to_string();
     };
 }
 
+// everything the message line reports
+#[derive(SystemParam)]
+struct UiMessageEvents<'w, 's> {
+    rejected: EventReader<'w, 's, PlacementRejected>,
+    payroll: EventReader<'w, 's, PayrollCompleted>,
+    quit: EventReader<'w, 's, WorkerQuit>,
+    sold: EventReader<'w, 's, GoodsSold>,
+    bought: EventReader<'w, 's, GoodsBought>,
+    purchase_rejected: EventReader<'w, 's, PurchaseRejected>,
+    rent: EventReader<'w, 's, RentCollected>,
+    upkeep_unpaid: EventReader<'w, 's, UpkeepUnpaid>,
+    disrepair: EventReader<'w, 's, BuildingDisrepair>,
+    ledger_closed: EventReader<'w, 's, LedgerClosed>
+}
+
+// shows the last thing that happened (a rejected placement, the payroll...), clears itself after a few seconds
+fn ui_message_text(
+    time: Res<Time<Real>>,
+    mut clear_timer: Local<Timer>,
+    mut events: UiMessageEvents,
+    mut message: Query<&mut Text, With<UiMessage>>
+) {
+    let Ok(mut text) = message.single_mut() else { return; };
+
+    let mut latest: Option<String> = Option::None;
+
+    if let Some(last) = events.rejected.read().last() { latest = Some(format!("{:?} at {:?}: {}", last.item, last.tile, last.reason)); }
+    if let Some(last) = events.bought.read().last() { latest = Some(format!("bought {} {:?} for {} coins", last.amount, last.item, last.coins)); }
+    if let Some(last) = events.purchase_rejected.read().last() { latest = Some(format!("{:?}: {}", last.item, last.reason)); }
+    if let Some(last) = events.sold.read().last() { latest = Some(format!("sold {} {:?} for {} coins", last.amount, last.item, last.coins)); }
+    if let Some(last) = events.payroll.read().last() { latest = Some(format!("payday: {} paid ({} coins), {} unpaid", last.paid, last.total, last.unpaid)); }
+    if let Some(last) = events.ledger_closed.read().last() { latest = Some(format!("day closed: +{} -{} coins", last.income, last.expenses)); }
+    if let Some(last) = events.rent.read().last().filter(|r| r.tenants > 0) { latest = Some(format!("rent: {} coins from {} tenants", last.total, last.tenants)); }
+    if let Some(last) = events.upkeep_unpaid.read().last() { latest = Some(format!("upkeep unpaid for {} day(s), {} building(s) unpaid", last.days, last.unpaid_buildings)); }
+    if let Some(last) = events.disrepair.read().last() { latest = Some(format!("{:?} fell into disrepair", last.item)); }
+
+    let quitters = events.quit.read().count();
+    if quitters > 0 { latest = Some(format!("{} worker(s) quit, wages unpaid", quitters)); }
+
+    if let Some(latest) = latest {
+        text.0 = latest;
+        *clear_timer = Timer::from_seconds(3., TimerMode::Once);
+        return;
+    }
+
+    clear_timer.tick(time.delta());
+    if clear_timer.just_finished() { text.0 = "".to_string(); }
+}
+
+// hidden until there is a built storage
+fn ui_town_stock_text(stock: Res<TownStock>, mut panel: Query<(&mut Text, &mut Node), With<UiTownStock>>) {
+    if !stock.is_changed() { return; }
+    let Ok((mut text, mut node)) = panel.single_mut() else { return; };
+
+    let mut items: Vec<(&ItemType, &i32)> = stock.items.iter().collect();
+    items.sort_by_key(|(item, _)| format!("{:?}", item));
+
+    let used: i32 = stock.items.values().sum();
+    text.0 = format!("Town stock {}/{}", used, stock.capacity);
+    for (item, amount) in items { text.0 += &format!("\n{:?} {}", item, amount); }
+
+    node.display = if stock.capacity > 0 { Display::Flex } else { Display::None };
+}
+
+fn ui_treasury_text(treasury: Res<Treasury>, mut treasury_text: Query<&mut Text, With<UiTreasury>>) {
+    if !treasury.is_changed() { return; }
+    if let Ok(mut text) = treasury_text.single_mut() { text.0 = format!("$ {}", treasury.coins); }
+}
+
 fn highlight_slot_selected(item_selected: Res<ItemSelected>,mut ui_slots: Query<(&mut BackgroundColor, Entity),With<UiItemSlotButton>>) {
     for (mut bgc, entity) in &mut ui_slots {
         if item_selected.ui_entity == entity { bgc.0 = Color::srgb(0.7, 0.7, 0.7); continue; }
@@ -324,17 +508,18 @@ fn dyn_ui_selection(
     item_selected: Res<ItemSelected>,
     mouse_position: Res<MyWorldCoords>,
     buildings_tuple: Res<BuildingTuple>,
-    building_coords: Res<BuildingCoords>,
+    crop_registry: Res<CropRegistry>,
     input: Res<ButtonInput<MouseButton>>,
     world_entities: Query<(&Transform, Entity), With<HasDynamicMenu>>
 ) {
-    if input.just_pressed(MouseButton::Left) && item_selected.selected == ItemType::None && 
-        building_coords.data.contains(&(mouse_position.0.x as i32, mouse_position.0.y as i32)) {
+    // the building tuples hold every occupied tile with what is on it
+    if input.just_pressed(MouseButton::Left) && item_selected.selected == ItemType::None {
 
         if let Some(building) = buildings_tuple.data.iter().find(|bt|bt.0 == (mouse_position.0.x as i32, mouse_position.0.y as i32)) {
             
             let menu_selected = match building.1 {
-                ItemType::Dirt => { (ItemType::Dirt, true, 2) }
+                // one button per crop and the close button, crops past MAX_CROP_BUTTONS are not shown
+                ItemType::Dirt => { (ItemType::Dirt, true, crop_registry.crops.len().min(MAX_CROP_BUTTONS) as i32 + 1) }
                 _ => { (ItemType::None, false, 0) }
             };
 
@@ -348,7 +533,13 @@ fn dyn_ui_selection(
     }
 }
 
-fn display_dyn_ui_selected(mut cmm: Commands,mut dyn_ui: ResMut<DynamicUi>,dyn_menu_buttons: Query<&DynamicButtonId>) {
+fn display_dyn_ui_selected(
+    mut cmm: Commands,
+    mut dyn_ui: ResMut<DynamicUi>,
+    crop_registry: Res<CropRegistry>,
+    crop_definitions: Res<Assets<CropDefinition>>,
+    dyn_menu_buttons: Query<&DynamicButtonId>
+) {
     const INVENTORY_ROW_GAP: f32 = 10.0;
     const BUTTON_SLOT_SIZE: f32 = 75.0;
 
@@ -392,7 +583,17 @@ fn display_dyn_ui_selected(mut cmm: Commands,mut dyn_ui: ResMut<DynamicUi>,dyn_m
                                     5 => { DynamicButtonId::ButtonSix },
                                     _ => { DynamicButtonId::ButtonOne }
                                 }
-                            ));
+                            )).with_children(|button| {
+                                if i == dyn_ui.button_count - 1 {
+                                    button.spawn((Text::new("x"), TextFont { font_size: 20., ..default() }));
+                                    return;
+                                }
+                                // crop buttons show the grown crop
+                                let crop = crop_registry.crops.get(i as usize).and_then(|id| crop_definitions.get(*id));
+                                if let Some(sprite) = crop.and_then(|def| def.sprites.last()) {
+                                    button.spawn((ImageNode::new(sprite.clone()), Node { width: Val::Percent(100.), height: Val::Percent(100.), ..default() }));
+                                }
+                            });
                         }
                     }).id();
                 });
@@ -406,7 +607,9 @@ fn display_dyn_ui_selected(mut cmm: Commands,mut dyn_ui: ResMut<DynamicUi>,dyn_m
 
 // actions of the actual dynamic menu displayed
 fn dynamic_menu_actions(
-    dyn_ui: Res<DynamicUi>,
+    mut cmm: Commands,
+    mut dyn_ui: ResMut<DynamicUi>,
+    crop_registry: Res<CropRegistry>,
     input: Res<ButtonInput<MouseButton>>,
     mut crops: Query<(&mut PreparedDirtData, Entity), With<PreparedDirtData>>,
     dyn_button: Query<(&Interaction,&DynamicButtonId), (With<DynamicButtonId>, Without<UiItemSlotButton>)>
@@ -415,18 +618,104 @@ fn dynamic_menu_actions(
         match dyn_ui.selected {
             ItemType::Dirt => {
                 if let Some(button_pressed) = dyn_button.iter().find(|b|*b.0 == Interaction::Pressed) {
-                    match button_pressed.1 {
-                        DynamicButtonId::ButtonOne => {
-                            if let Some(mut crop) = crops.iter_mut().find(|c|c.1 == dyn_ui.world_entity) {
-                                crop.0.crop_type_selected = true;
-                            };
-                        },
-                        DynamicButtonId::ButtonTwo => {},
-                        _ => {}
+                    // the last button closes the menu, it never selects a crop
+                    if button_pressed.1.index() as i32 == dyn_ui.button_count - 1 {
+                        cmm.entity(dyn_ui.actual_ui_entity).despawn();
+                        dyn_ui.selected = ItemType::None;
+                        return;
+                    }
+
+                    // the crop can only be changed while nothing is planted
+                    if let Some(crop_id) = crop_registry.crops.get(button_pressed.1.index())
+                        && let Some(mut crop) = crops.iter_mut().find(|c|c.1 == dyn_ui.world_entity && !c.0.planted) {
+                        crop.0.crop_type = Some(*crop_id);
                     }
                 };
             }
             _ => {}
         }
     }
-}
\ No newline at end of file
+}
+
+// [[ SHOP ]]
+
+fn spawn_shop_panel(cmm: &mut Commands) {
+    cmm.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(30.),
+            left: Val::Px(8.),
+            width: Val::Px(160.),
+            display: Display::None,
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.),
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.3,0.3,0.3)),
+        GlobalZIndex(5),
+        Interaction::default(),
+        UiShopPanel
+    ));
+}
+
+// one row per tradeable item, crops loaded later get their rows when their price is added
+fn ui_shop_rows(
+    mut cmm: Commands,
+    prices: Res<MarketPrices>,
+    panel: Query<Entity, With<UiShopPanel>>,
+    rows: Query<&UiShopItem>
+) {
+    if !prices.is_changed() { return; }
+    let Ok(panel) = panel.single() else { return; };
+
+    for item in prices.tradeable_items().filter(|item| !rows.iter().any(|row| row.0 == *item)) {
+        cmm.entity(panel).with_child((
+            Node { height: Val::Px(24.), padding: UiRect::horizontal(Val::Px(6.)), align_items: AlignItems::Center, ..default() },
+            BackgroundColor(Color::srgb(RGBINVSLOT.0,RGBINVSLOT.1,RGBINVSLOT.2)),
+            Button,
+            TextFont { font_size: 12., ..default() },
+            Text::new(format!("{:?}  $ {}", item, prices.price(item).unwrap_or(0))),
+            UiShopItem(item)
+        ));
+    }
+}
+
+fn toggle_shop(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<UiShopButton>)>,
+    mut panel: Query<&mut Node, With<UiShopPanel>>
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) { return; }
+    let Ok(mut node) = panel.single_mut() else { return; };
+
+    node.display = if node.display == Display::None { Display::Flex } else { Display::None };
+}
+
+fn shop_buy_items(
+    input: Res<ButtonInput<KeyCode>>,
+    mut prices: ResMut<MarketPrices>,
+    mut funds: TreasuryAccess,
+    mut inventory: ResMut<PlayerInventory>,
+    mut bought: EventWriter<GoodsBought>,
+    mut rejected: EventWriter<PurchaseRejected>,
+    rows: Query<(&Interaction, &UiShopItem), Changed<Interaction>>
+) {
+    let amount = if input.pressed(KeyCode::ShiftLeft) || input.pressed(KeyCode::ShiftRight) { 10 } else { 1 };
+
+    for (interaction, row) in &rows {
+        if *interaction != Interaction::Pressed { continue; }
+
+        match buy_into_inventory(row.0, amount, &mut prices, &mut funds, &mut inventory) {
+            Ok(coins) => { bought.write(GoodsBought { item: row.0, amount, coins }); },
+            Err(reason) => { rejected.write(PurchaseRejected { item: row.0, reason }); }
+        }
+    }
+}
+
+fn ui_shop_prices(prices: Res<MarketPrices>, mut rows: Query<(&UiShopItem, &mut Text)>) {
+    if !prices.is_changed() { return; }
+
+    for (row, mut text) in &mut rows {
+        text.0 = format!("{:?}  $ {}", row.0, prices.price(row.0).unwrap_or(0));
+    }
+}
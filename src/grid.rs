@@ -1,19 +1,257 @@
-use std::collections::HashSet;
-use noise::{NoiseFn, Perlin};
+use std::collections::{HashMap, HashSet};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
 
-use bevy::prelude::*;
+use bevy::{asset::{io::{AssetReaderError, AssetSourceId}, RenderAssetUsages}, prelude::*, render::render_resource::{Extent3d, TextureDimension, TextureFormat}, tasks::{block_on, poll_once, AsyncComputeTaskPool, Task}};
 
-use crate::camera::MainCameraActualPosition;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-#[derive(Debug, Component)]
-enum SquareType {
-    Grass,
+use crate::{biome::Biome, camera::MainCameraActualPosition, nodes::{node_for_tile, NodeKind, ResourceNode}};
+
+const WORLDGEN_SETTINGS_PATH: &str = "worldgen.ron"; // in the asset folder
+const CHUNK_EDITS_SAVE_PATH: &str = "saves/chunk_edits.ron";
+
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SquareType {
+    DeepWater,
+    Water,
     Sand,
-    Water
+    Grass,
+    DarkGrass,
+    Rock,
+    Tilled
+}
+
+impl SquareType {
+    pub fn is_walkable(&self) -> bool {
+        !matches!(self, SquareType::DeepWater | SquareType::Water)
+    }
+}
+
+// upper noise value of each terrain band, anything above `dark_grass` is rock
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TerrainThresholds {
+    pub deep_water: f64,
+    pub water: f64,
+    pub sand: f64,
+    pub grass: f64,
+    pub dark_grass: f64
+}
+
+impl Default for TerrainThresholds {
+    fn default() -> Self {
+        TerrainThresholds { deep_water: -0.5, water: -0.1, sand: 0.0, grass: 0.4, dark_grass: 0.8 }
+    }
+}
+
+// loaded from WORLDGEN_SETTINGS_PATH at startup, missing fields use the defaults
+#[derive(Debug, Clone, Resource, Deserialize)]
+#[serde(default)]
+pub struct WorldGenSettings {
+    pub seed: Option<u32>, // none picks a random seed for every new game
+    pub scale: f64,
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub persistence: f64,
+    pub biome_scale: f64, // temperature and moisture change slower than the height
+    pub thresholds: TerrainThresholds
+}
+
+impl Default for WorldGenSettings {
+    fn default() -> Self {
+        WorldGenSettings { seed: Option::None, scale: 200.0, octaves: 4, lacunarity: 2.0, persistence: 0.5, biome_scale: 600.0, thresholds: TerrainThresholds::default() }
+    }
+}
+
+impl WorldGenSettings {
+    // read through the default asset source so the path resolves like any other asset
+    // the terrain is needed at startup so it isn't loaded as an asset
+    pub fn load(asset_server: &AssetServer, path: &str) -> Self {
+        let settings = match read_asset_bytes(asset_server, path) {
+            Ok(file) => ron::de::from_bytes(&file).unwrap_or_else(|e| {
+                warn!("[worldgen] invalid {}: {}, using defaults", path, e);
+                WorldGenSettings::default()
+            }),
+            Err(e) => {
+                warn!("[worldgen] could not read {}: {}, using defaults", path, e);
+                WorldGenSettings::default()
+            }
+        };
+
+        // always store the seed used so the map can be generated again
+        WorldGenSettings { seed: Some(settings.seed.unwrap_or_else(rand::random)), ..settings }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed.unwrap_or_default()
+    }
+}
+
+fn read_asset_bytes(asset_server: &AssetServer, path: &str) -> Result<Vec<u8>, AssetReaderError> {
+    let source = asset_server.get_source(AssetSourceId::Default)
+        .map_err(|e| AssetReaderError::Io(std::io::Error::other(e.to_string()).into()))?;
+
+    block_on(async {
+        let mut reader = source.reader().read(std::path::Path::new(path)).await?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(bytes)
+    })
+}
+
+// pure noise side of the terrain, cloned into the chunk generation tasks
+#[derive(Debug, Clone)]
+struct TerrainGenerator {
+    seed: u32,
+    noise: Fbm<Perlin>,
+    temperature: Fbm<Perlin>,
+    moisture: Fbm<Perlin>,
+    scale: f64,
+    biome_scale: f64,
+    thresholds: TerrainThresholds
+}
+
+impl TerrainGenerator {
+    fn new(settings: &WorldGenSettings) -> Self {
+        let fbm = |seed: u32| Fbm::<Perlin>::new(seed)
+            .set_octaves(settings.octaves)
+            .set_lacunarity(settings.lacunarity)
+            .set_persistence(settings.persistence);
+
+        TerrainGenerator {
+            seed: settings.seed(),
+            noise: fbm(settings.seed()),
+            temperature: fbm(settings.seed().wrapping_add(1)),
+            moisture: fbm(settings.seed().wrapping_add(2)),
+            scale: settings.scale,
+            biome_scale: settings.biome_scale,
+            thresholds: settings.thresholds.clone()
+        }
+    }
+
+    fn terrain_at(&self, tile: (i32,i32)) -> SquareType {
+        let value = self.noise.get([tile.0 as f64 / self.scale, tile.1 as f64 / self.scale]);
+        assign_square_type(value, &self.thresholds)
+    }
+
+    fn biome_at(&self, tile: (i32,i32)) -> Biome {
+        let point = [tile.0 as f64 / self.biome_scale, tile.1 as f64 / self.biome_scale];
+        Biome::classify(self.temperature.get(point), self.moisture.get(point))
+    }
+
+    // the same chunk always gets the same nodes, the rng is seeded with the world seed and the chunk coords
+    fn chunk_nodes(&self, chunk_coords: (i32,i32), delta: &ChunkDelta) -> Vec<((i32,i32), NodeKind)> {
+        let chunk_seed = (self.seed as u64) ^ (chunk_coords.0 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (chunk_coords.1 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        let mut rng = StdRng::seed_from_u64(chunk_seed);
+        let mut nodes = vec![];
+
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                let tile = (chunk_coords.0 * CHUNK_SIZE + x, chunk_coords.1 * CHUNK_SIZE + y);
+                let roll: f32 = rng.random(); // rolled for every tile so removing a node doesn't move the others
+
+                if delta.tiles.contains_key(&tile) || delta.harvested_nodes.contains(&tile) { continue; }
+
+                let near_water = [(1,0),(-1,0),(0,1),(0,-1)].iter()
+                    .any(|(dx,dy)| !self.terrain_at((tile.0 + dx, tile.1 + dy)).is_walkable());
+
+                if let Some(kind) = node_for_tile(self.terrain_at(tile), self.biome_at(tile), near_water, roll) {
+                    nodes.push((tile, kind));
+                }
+            }
+        }
+
+        nodes
+    }
+}
+
+// tiles changed by the player on top of the generated terrain
+// grouped by chunk so a chunk being generated only needs its own delta
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChunkEdits {
+    chunks: HashMap<(i32,i32), ChunkDelta>
 }
 
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkDelta {
+    pub tiles: HashMap<(i32,i32), SquareType>,
+    pub harvested_nodes: HashSet<(i32,i32)>
+}
+
+impl ChunkEdits {
+    pub fn tile(&self, tile: (i32,i32)) -> Option<SquareType> {
+        self.chunks.get(&tile_to_chunk(tile)).and_then(|delta| delta.tiles.get(&tile).copied())
+    }
+
+    pub fn chunk(&self, chunk_coords: (i32,i32)) -> Option<&ChunkDelta> {
+        self.chunks.get(&chunk_coords)
+    }
+
+    fn set_tile(&mut self, tile: (i32,i32), square: SquareType) {
+        self.chunks.entry(tile_to_chunk(tile)).or_default().tiles.insert(tile, square);
+    }
+
+    fn mark_node_harvested(&mut self, tile: (i32,i32)) {
+        self.chunks.entry(tile_to_chunk(tile)).or_default().harvested_nodes.insert(tile);
+    }
+}
+
+// terrain lookup for any tile, loaded or not
+// generated tiles only depend on the generation settings, edited tiles come from the ChunkEdits
 #[derive(Debug, Resource)]
-struct PerlinInstance { value: Perlin }
+pub struct TerrainMap {
+    generator: TerrainGenerator,
+    edits: ChunkEdits,
+    dirty_chunks: HashSet<(i32,i32)> // chunks that have to be generated again to show the edits
+}
+
+impl TerrainMap {
+    pub fn new(settings: &WorldGenSettings) -> Self {
+        TerrainMap { generator: TerrainGenerator::new(settings), edits: ChunkEdits::default(), dirty_chunks: HashSet::new() }
+    }
+
+    pub fn terrain_at(&self, tile: (i32,i32)) -> SquareType {
+        self.edits.tile(tile).unwrap_or_else(|| self.generator.terrain_at(tile))
+    }
+
+    pub fn biome_at(&self, tile: (i32,i32)) -> Biome {
+        self.generator.biome_at(tile)
+    }
+
+    pub fn is_walkable(&self, tile: (i32,i32)) -> bool {
+        self.terrain_at(tile).is_walkable()
+    }
+
+    pub fn set_tile(&mut self, tile: (i32,i32), square: SquareType) {
+        self.edits.set_tile(tile, square);
+        self.dirty_chunks.insert(tile_to_chunk(tile));
+    }
+
+    // the node entity is despawned by whoever harvested it, the chunk doesn't need to be generated again
+    pub fn mark_node_harvested(&mut self, tile: (i32,i32)) {
+        self.edits.mark_node_harvested(tile);
+    }
+
+    pub fn edits(&self) -> &ChunkEdits {
+        &self.edits
+    }
+
+    pub fn replace_edits(&mut self, edits: ChunkEdits) {
+        self.dirty_chunks.extend(self.edits.chunks.keys().chain(edits.chunks.keys()));
+        self.edits = edits;
+    }
+}
+
+// world position to the tile that contains it (tiles are centered on integer coords)
+pub fn world_to_tile(pos: Vec2) -> (i32,i32) {
+    (pos.x.round() as i32, pos.y.round() as i32)
+}
+
+pub fn tile_to_chunk(tile: (i32,i32)) -> (i32,i32) {
+    (tile.0.div_euclid(CHUNK_SIZE), tile.1.div_euclid(CHUNK_SIZE))
+}
 
 #[derive(Resource)]
 struct LoadedChunks(HashSet<(i32, i32)>);
@@ -21,6 +259,16 @@ struct LoadedChunks(HashSet<(i32, i32)>);
 #[derive(Resource)]
 struct DesiredChunks(HashSet<(i32,i32)>);
 
+// chunks being generated on the AsyncComputeTaskPool
+#[derive(Resource)]
+struct PendingChunks(HashMap<(i32,i32), Task<ChunkData>>);
+
+struct ChunkData {
+    chunk_coords: (i32,i32),
+    pixels: Vec<u8>,
+    nodes: Vec<((i32,i32), NodeKind)>
+}
+
 #[derive(Component)]
 pub struct ChunkMarker { pub chunk_coords: (i32, i32) }
 
@@ -30,14 +278,24 @@ impl Plugin for MyGridPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(LoadedChunks(HashSet::new()));
         app.insert_resource(DesiredChunks(HashSet::new()));
-        app.insert_resource(PerlinInstance{value:Perlin::new(4)});
-        
-        app.add_systems(Update, (generate_new_chunk_data, spawn_new_chunks, delete_old_chunks));
+        app.insert_resource(PendingChunks(HashMap::new()));
+
+        app.add_systems(Startup, load_world_gen_settings);
+        app.add_systems(Update, save_load_terrain_edits);
+        app.add_systems(Update, (generate_new_chunk_data, refresh_edited_chunks, request_new_chunks, spawn_new_chunks, delete_old_chunks).chain());
     }
 }
 
+fn load_world_gen_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let settings = WorldGenSettings::load(&asset_server, WORLDGEN_SETTINGS_PATH);
+    info!("[worldgen] seed {}", settings.seed());
+
+    commands.insert_resource(TerrainMap::new(&settings));
+    commands.insert_resource(settings);
+}
+
 const CHUNK_SIZE: i32 = 8;
-const RENDER_DISTANCE: i32 = 4;
+const RENDER_DISTANCE: i32 = 8;
 
 fn generate_new_chunk_data(mut desired_chunks:ResMut<DesiredChunks>,cam_main:Res<MainCameraActualPosition>) {
     let camera_chunk_x = (cam_main.0.x / CHUNK_SIZE as f32).floor() as i32;
@@ -51,42 +309,159 @@ fn generate_new_chunk_data(mut desired_chunks:ResMut<DesiredChunks>,cam_main:Res
     }
 }
 
-fn spawn_new_chunks(mut commands:Commands,mut loaded_chunks:ResMut<LoadedChunks>,desired_chunks:Res<DesiredChunks>,perlin:Res<PerlinInstance>,asse:Res<AssetServer>) {
+// edited chunks are generated again with their delta, a loaded chunk keeps showing the old tiles until then
+fn refresh_edited_chunks(mut terrain:ResMut<TerrainMap>,loaded_chunks:Res<LoadedChunks>,mut pending_chunks:ResMut<PendingChunks>) {
+    if terrain.dirty_chunks.is_empty() { return; }
+
+    let dirty_chunks: Vec<(i32,i32)> = terrain.dirty_chunks.drain().collect();
+    for chunk_coords in dirty_chunks {
+        // a task started before the edit would show the old tiles, replacing it drops (cancels) it
+        if loaded_chunks.0.contains(&chunk_coords) {
+            pending_chunks.0.insert(chunk_coords, spawn_chunk_task(chunk_coords, &terrain));
+        } else {
+            pending_chunks.0.remove(&chunk_coords);
+        }
+    }
+}
+
+// debug hotkeys: F5 saves and F9 loads only the terrain edits (tilled tiles, harvested nodes)
+// buildings, dirt, workers and inventories are not saved, and the edits only fit the map (seed) they were made on
+fn save_load_terrain_edits(input: Res<ButtonInput<KeyCode>>, mut terrain: ResMut<TerrainMap>) {
+    if input.just_pressed(KeyCode::F5) {
+        let saved = ron::ser::to_string_pretty(terrain.edits(), ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                std::fs::create_dir_all("saves").map_err(|e| e.to_string())?;
+                std::fs::write(CHUNK_EDITS_SAVE_PATH, file).map_err(|e| e.to_string())
+            });
+
+        match saved {
+            Ok(_) => info!("[chunk_edits] terrain edits saved to {}", CHUNK_EDITS_SAVE_PATH),
+            Err(e) => warn!("[chunk_edits] could not save: {}", e)
+        }
+    }
+
+    if input.just_pressed(KeyCode::F9) {
+        let loaded = std::fs::read_to_string(CHUNK_EDITS_SAVE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|file| ron::from_str::<ChunkEdits>(&file).map_err(|e| e.to_string()));
+
+        match loaded {
+            Ok(edits) => { terrain.replace_edits(edits); info!("[chunk_edits] terrain edits loaded from {}", CHUNK_EDITS_SAVE_PATH); },
+            Err(e) => warn!("[chunk_edits] could not load: {}", e)
+        }
+    }
+}
+
+// noise sampling is slow so every missing chunk is generated in a task
+fn request_new_chunks(loaded_chunks:Res<LoadedChunks>,desired_chunks:Res<DesiredChunks>,mut pending_chunks:ResMut<PendingChunks>,terrain:Res<TerrainMap>) {
     for &chunk_coords in desired_chunks.0.iter() {
-        if !loaded_chunks.0.contains(&chunk_coords) {
-            let (chunk_x, chunk_y) = chunk_coords;
-            for x in 0..CHUNK_SIZE {
-                for y in 0..CHUNK_SIZE {
-                    let world_x = (chunk_x * CHUNK_SIZE + x) as f32;
-                    let world_y = (chunk_y * CHUNK_SIZE + y) as f32; 
-
-                    let noise_x = (chunk_x * CHUNK_SIZE + x) as f64 / 200.0;
-                    let noise_y = (chunk_y * CHUNK_SIZE + y) as f64 / 200.0;
-
-                    let noise_value = perlin.value.get([noise_x as f64, noise_y as f64]);
-
-                    commands.spawn((
-                        Transform {
-                            translation: Vec3::new(world_x, world_y, 0.),
-                            scale: Vec3::new(1., 1., 1.),
-                            ..default()
-                        },
-                        Sprite {
-                            color: assign_color(noise_value),
-                            // image: assign_image(noise_value, &asse),//asse.load("gd02.png"),
-                            custom_size: Some(Vec2::new(1.,1.)),
-                            ..default()
-                        },
-                        ChunkMarker { chunk_coords },
-                    ));
-                }
+        if !loaded_chunks.0.contains(&chunk_coords) && !pending_chunks.0.contains_key(&chunk_coords) {
+            pending_chunks.0.insert(chunk_coords, spawn_chunk_task(chunk_coords, &terrain));
+        }
+    }
+}
+
+fn spawn_chunk_task(chunk_coords: (i32,i32), terrain: &TerrainMap) -> Task<ChunkData> {
+    let generator = terrain.generator.clone();
+    let delta = terrain.edits.chunk(chunk_coords).cloned().unwrap_or_default();
+    AsyncComputeTaskPool::get().spawn(async move { generate_chunk_data(chunk_coords, &generator, &delta) })
+}
+
+// every chunk is one sprite with a CHUNK_SIZE x CHUNK_SIZE texture, one pixel per tile
+// a refreshed chunk only gets its new image, nodes that are still there keep their entity (and their jobs)
+fn spawn_new_chunks(
+    mut commands:Commands,
+    mut loaded_chunks:ResMut<LoadedChunks>,
+    mut pending_chunks:ResMut<PendingChunks>,
+    mut images:ResMut<Assets<Image>>,
+    mut chunk_sprites:Query<(&ChunkMarker, &mut Sprite), Without<ResourceNode>>,
+    nodes:Query<(&ResourceNode, &ChunkMarker, Entity)>
+) {
+    let mut finished: Vec<ChunkData> = vec![];
+
+    pending_chunks.0.retain(|_, task| {
+        match block_on(poll_once(task)) {
+            Some(chunk_data) => { finished.push(chunk_data); false },
+            None => true
+        }
+    });
+
+    for chunk_data in finished {
+        let (chunk_x, chunk_y) = chunk_data.chunk_coords;
+        // tiles are centered on integer coords so the chunk center is half a tile off
+        let center_offset = (CHUNK_SIZE - 1) as f32 / 2.;
+
+        let image = images.add(build_chunk_image(chunk_data.pixels));
+
+        match chunk_sprites.iter_mut().find(|(marker, _)| marker.chunk_coords == chunk_data.chunk_coords) {
+            Some((_, mut sprite)) => { sprite.image = image; },
+            None => {
+                commands.spawn((
+                    Transform::from_xyz((chunk_x * CHUNK_SIZE) as f32 + center_offset, (chunk_y * CHUNK_SIZE) as f32 + center_offset, 0.),
+                    Sprite {
+                        image,
+                        custom_size: Some(Vec2::splat(CHUNK_SIZE as f32)),
+                        ..default()
+                    },
+                    ChunkMarker { chunk_coords: chunk_data.chunk_coords },
+                ));
             }
-            loaded_chunks.0.insert(chunk_coords);
+        }
+
+        let mut old_nodes: HashMap<(i32,i32), Entity> = nodes.iter()
+            .filter(|(_, marker, _)| marker.chunk_coords == chunk_data.chunk_coords)
+            .map(|(node, _, entity)| (node.tile, entity))
+            .collect();
+
+        // nodes carry the chunk marker too so they are unloaded with their chunk
+        for (tile, kind) in chunk_data.nodes {
+            if old_nodes.remove(&tile).is_some() { continue; }
+            commands.spawn((
+                Transform::from_xyz(tile.0 as f32, tile.1 as f32, 1.),
+                kind.sprite(),
+                ResourceNode { kind, tile },
+                ChunkMarker { chunk_coords: chunk_data.chunk_coords },
+            ));
+        }
+        // harvested in loaded chunk edits
+        for entity in old_nodes.into_values() { commands.entity(entity).despawn(); }
+
+        loaded_chunks.0.insert(chunk_data.chunk_coords);
+    }
+}
+
+// generated terrain with the player edits of the chunk applied on top
+fn generate_chunk_data(chunk_coords: (i32,i32), generator: &TerrainGenerator, delta: &ChunkDelta) -> ChunkData {
+    let (chunk_x, chunk_y) = chunk_coords;
+    let mut pixels: Vec<u8> = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE * 4) as usize);
+
+    // image rows go from top to bottom, world y goes up
+    for y in (0..CHUNK_SIZE).rev() {
+        for x in 0..CHUNK_SIZE {
+            let tile = (chunk_x * CHUNK_SIZE + x, chunk_y * CHUNK_SIZE + y);
+            let square = delta.tiles.get(&tile).copied().unwrap_or_else(|| generator.terrain_at(tile));
+            pixels.extend_from_slice(&generator.biome_at(tile).color(square).to_srgba().to_u8_array());
         }
     }
+
+    ChunkData { chunk_coords, pixels, nodes: generator.chunk_nodes(chunk_coords, delta) }
 }
 
-fn delete_old_chunks(mut commands:Commands,query: Query<(Entity, &ChunkMarker), With<ChunkMarker>>,desired_chunks:Res<DesiredChunks>,mut loaded_chunks:ResMut<LoadedChunks>) {
+fn build_chunk_image(pixels: Vec<u8>) -> Image {
+    Image::new(
+        Extent3d { width: CHUNK_SIZE as u32, height: CHUNK_SIZE as u32, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD
+    )
+}
+
+fn delete_old_chunks(mut commands:Commands,query: Query<(Entity, &ChunkMarker), With<ChunkMarker>>,desired_chunks:Res<DesiredChunks>,mut loaded_chunks:ResMut<LoadedChunks>,mut pending_chunks:ResMut<PendingChunks>) {
+    // dropping the task cancels it
+    pending_chunks.0.retain(|chunk_coords, _| desired_chunks.0.contains(chunk_coords));
+
     for (entity, marker) in query.iter() {
         if !desired_chunks.0.contains(&marker.chunk_coords) {
             commands.entity(entity).despawn();
@@ -95,22 +470,18 @@ fn delete_old_chunks(mut commands:Commands,query: Query<(Entity, &ChunkMarker),
     }
 } 
 
-fn assign_color(value: f64) -> Color {
-    if (-1.2..=-0.8).contains(&value) {
-        Color::srgb(0.0, 0.0, 0.5)
-    } else if (-0.8..=-0.5).contains(&value) {
-        Color::srgb(0.0, 0.2, 0.8)
-    } else if (-0.5..=-0.1).contains(&value) {
-        Color::srgb(0.3, 0.5, 1.0)
-    } else if (-0.1..=0.0).contains(&value) {
-        Color::srgb(1.0, 0.9, 0.6)
-    } else if (0.0..=0.4).contains(&value) {
-        Color::srgb(0.56, 0.83, 0.43)
-    } else if (0.4..=0.8).contains(&value) {
-        Color::srgb(0.4, 0.65, 0.28)
-    } else if (0.8..=1.2).contains(&value) {
-        Color::srgb(0.8, 0.8, 0.8)
+fn assign_square_type(value: f64, thresholds: &TerrainThresholds) -> SquareType {
+    if value <= thresholds.deep_water {
+        SquareType::DeepWater
+    } else if value <= thresholds.water {
+        SquareType::Water
+    } else if value <= thresholds.sand {
+        SquareType::Sand
+    } else if value <= thresholds.grass {
+        SquareType::Grass
+    } else if value <= thresholds.dark_grass {
+        SquareType::DarkGrass
     } else {
-        Color::srgb(1.0, 1.0, 1.0)
+        SquareType::Rock
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,173 @@
+use bevy::{ecs::entity::Entities, platform::collections::HashSet, prelude::*};
+
+use crate::gamestate::GameState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JobKind {
+    Plant,
+    Tend,
+    Harvest,
+    Haul,
+    Build,
+    Operate // staffing a production building
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Skill {
+    Farming,
+    Gathering,
+    Construction,
+    Hauling,
+    Crafting
+}
+
+impl JobKind {
+    // seconds of work at the target, continuous jobs don't use it
+    pub fn work_seconds(&self) -> f32 {
+        match self {
+            JobKind::Plant => 3.,
+            JobKind::Tend => 0.,
+            JobKind::Harvest => 3.,
+            JobKind::Haul => 1.,
+            JobKind::Build => 10.,
+            JobKind::Operate => 0.
+        }
+    }
+
+    // the worker stays until the job is taken off the board (crop ready, batch done, building closed...)
+    pub fn is_continuous(&self) -> bool {
+        matches!(self, JobKind::Tend | JobKind::Operate)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    pub target: Entity,
+    pub tile: (i32,i32),
+    pub priority: i32, // higher goes first
+    pub skill: Skill,
+    pub claimed_by: Option<Entity>
+}
+
+// every task in town, buildings and crops post jobs and idle workers claim them
+#[derive(Debug, Resource, Default)]
+pub struct JobBoard {
+    jobs: Vec<Job>,
+    posted: HashSet<(JobKind, Entity)>, // only one job of each kind per target
+    next_id: u64
+}
+
+impl JobBoard {
+    pub fn post(&mut self, kind: JobKind, target: Entity, tile: (i32,i32), priority: i32, skill: Skill) {
+        if !self.posted.insert((kind, target)) { return; }
+
+        self.next_id += 1;
+        self.jobs.push(Job { id: self.next_id, kind, target, tile, priority, skill, claimed_by: Option::None });
+    }
+
+    pub fn has_job(&self, kind: JobKind, target: Entity) -> bool {
+        self.posted.contains(&(kind, target))
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    // the highest priority job the worker can do, the nearest one between the same priority
+    // jobs on tiles the worker can't reach are skipped
+    pub fn claim_best(&mut self, worker: Entity, worker_tile: (i32,i32), skills: &WorkerSkills, is_reachable: impl Fn((i32,i32)) -> bool) -> Option<Job> {
+        let distance = |tile: (i32,i32)| (tile.0 - worker_tile.0).abs() + (tile.1 - worker_tile.1).abs();
+
+        let job = self.jobs.iter_mut()
+            .filter(|j| j.claimed_by.is_none() && skills.0.contains(&j.skill) && is_reachable(j.tile))
+            .max_by_key(|j| (j.priority, -distance(j.tile)))?;
+
+        job.claimed_by = Some(worker);
+        Some(*job)
+    }
+
+    pub fn is_claimed_by(&self, id: u64, worker: Entity) -> bool {
+        self.jobs.iter().any(|j| j.id == id && j.claimed_by == Some(worker))
+    }
+
+    pub fn claimed_job(&self, kind: JobKind, target: Entity) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.kind == kind && j.target == target && j.claimed_by.is_some())
+    }
+
+    // the job goes back to the board for another worker
+    pub fn release(&mut self, id: u64) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) { job.claimed_by = Option::None; }
+    }
+
+    pub fn release_worker(&mut self, worker: Entity) {
+        for job in self.jobs.iter_mut().filter(|j| j.claimed_by == Some(worker)) { job.claimed_by = Option::None; }
+    }
+
+    pub fn complete(&mut self, id: u64) {
+        if let Some(index) = self.jobs.iter().position(|j| j.id == id) {
+            let job = self.jobs.remove(index);
+            self.posted.remove(&(job.kind, job.target));
+        }
+    }
+
+    pub fn remove(&mut self, kind: JobKind, target: Entity) {
+        if self.posted.remove(&(kind, target)) { self.jobs.retain(|j| !(j.kind == kind && j.target == target)); }
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct WorkerSkills(pub HashSet<Skill>);
+
+impl Default for WorkerSkills {
+    fn default() -> Self {
+        WorkerSkills([Skill::Farming, Skill::Gathering, Skill::Construction, Skill::Hauling, Skill::Crafting].into_iter().collect())
+    }
+}
+
+// sent by the worker when the work at the target is done, every module applies the result of its own jobs
+#[derive(Debug, Event)]
+pub struct JobCompleted {
+    pub job: Job,
+    pub worker: Entity
+}
+
+pub struct MyJobPlugin;
+
+impl Plugin for MyJobPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(JobBoard::default());
+        app.add_event::<JobCompleted>();
+        // workers can be despawned in any state (clean_scene), so this isn't gated
+        app.add_systems(Update, release_jobs_of_despawned_workers);
+        app.add_systems(Update, remove_jobs_without_target.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, log_job_board);
+    }
+}
+
+// checks the claimants themselves, removal events would be missed while a state gate is closed
+fn release_jobs_of_despawned_workers(entities: &Entities, mut board: ResMut<JobBoard>) {
+    let gone: Vec<Entity> = board.jobs.iter().filter_map(|j| j.claimed_by).filter(|worker| !entities.contains(*worker)).collect();
+
+    for worker in gone {
+        board.release_worker(worker);
+    }
+}
+
+// the target was despawned (chunk unloaded, building removed...)
+fn remove_jobs_without_target(entities: &Entities, mut board: ResMut<JobBoard>) {
+    let orphans: Vec<(JobKind, Entity)> = board.jobs.iter().filter(|j| !entities.contains(j.target)).map(|j| (j.kind, j.target)).collect();
+
+    for (kind, target) in orphans {
+        board.remove(kind, target);
+    }
+}
+
+fn log_job_board(input: Res<ButtonInput<KeyCode>>, board: Res<JobBoard>) {
+    if input.just_pressed(KeyCode::KeyJ) {
+        for job in board.jobs() {
+            info!("[job_board] {:?}", job);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::{camera::MainCamera, world::WorldSettings};
+
+const OVERLAY_SIZE: f32 = 100_000.; // big enough to cover the view at any zoom
+const OVERLAY_Z: f32 = 500.; // above every world sprite
+
+// colors laid over the world, the alpha is how strong the tint is
+// change the resource to adjust the lighting
+#[derive(Debug, Resource, Clone)]
+pub struct LightingPalette {
+    pub night: Color,
+    pub dawn: Color,
+    pub day: Color,
+    pub dusk: Color
+}
+
+impl Default for LightingPalette {
+    fn default() -> Self {
+        LightingPalette {
+            night: Color::srgba(0.02, 0.03, 0.15, 0.6),
+            dawn: Color::srgba(0.9, 0.5, 0.3, 0.2),
+            day: Color::srgba(1., 1., 1., 0.),
+            dusk: Color::srgba(0.6, 0.25, 0.35, 0.3)
+        }
+    }
+}
+
+// sprite in front of the camera that tints the world, the ui is drawn on top of it
+#[derive(Component)]
+struct LightingOverlay;
+
+pub struct MyLightingPlugin;
+
+impl Plugin for MyLightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightingPalette>();
+        app.add_systems(PostStartup, spawn_lighting_overlay);
+        app.add_systems(Update, update_lighting);
+    }
+}
+
+// tint for the hour of the day (0.0..24.0), night until 5, dawn at 6:30, day from 8 to 17, dusk at 19 and night again from 21
+pub fn light_tint(time_of_day: f32, palette: &LightingPalette) -> Color {
+    let keys = [
+        (0., palette.night),
+        (5., palette.night),
+        (6.5, palette.dawn),
+        (8., palette.day),
+        (17., palette.day),
+        (19., palette.dusk),
+        (21., palette.night),
+        (24., palette.night)
+    ];
+    let hour = time_of_day.rem_euclid(24.);
+
+    let Some(i) = keys.windows(2).position(|w| hour >= w[0].0 && hour <= w[1].0) else { return palette.night; };
+    let ((from_hour, from), (to_hour, to)) = (keys[i], keys[i + 1]);
+    let t = (hour - from_hour) / (to_hour - from_hour);
+
+    Color::from(LinearRgba::from(from).mix(&LinearRgba::from(to), t))
+}
+
+fn spawn_lighting_overlay(mut cmm: Commands, camera: Single<Entity, With<MainCamera>>) {
+    cmm.entity(*camera).with_child((
+        LightingOverlay,
+        Sprite { color: Color::NONE, custom_size: Some(Vec2::splat(OVERLAY_SIZE)), ..default() },
+        Transform::from_xyz(0., 0., OVERLAY_Z)
+    ));
+}
+
+fn update_lighting(day: Res<WorldSettings>, palette: Res<LightingPalette>, mut overlay: Single<&mut Sprite, With<LightingOverlay>>) {
+    overlay.color = light_tint(day.time_of_day(), &palette);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Color, expected: Color) {
+        let (a, e) = (actual.to_linear().to_f32_array(), expected.to_linear().to_f32_array());
+        assert!(a.iter().zip(e.iter()).all(|(a, e)| (a - e).abs() < 1e-4), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn noon_is_day_without_tint() {
+        let palette = LightingPalette::default();
+        assert_close(light_tint(12., &palette), palette.day);
+        assert_eq!(light_tint(12., &palette).alpha(), 0.);
+    }
+
+    #[test]
+    fn keyframes_give_their_color() {
+        let palette = LightingPalette::default();
+        assert_close(light_tint(2., &palette), palette.night);
+        assert_close(light_tint(6.5, &palette), palette.dawn);
+    }
+
+    #[test]
+    fn midnight_wraps_without_a_jump() {
+        let palette = LightingPalette::default();
+        assert_close(light_tint(23.99, &palette), light_tint(0., &palette));
+    }
+
+    #[test]
+    fn out_of_range_hours_wrap_around() {
+        let palette = LightingPalette::default();
+        assert_close(light_tint(36., &palette), light_tint(12., &palette));
+        assert_close(light_tint(-2., &palette), light_tint(22., &palette));
+        assert_close(light_tint(24., &palette), light_tint(0., &palette));
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::{image::ImageSamplerDescriptor, prelude::*, render::{settings::{Backends, RenderCreation, WgpuSettings}, RenderPlugin}, window::WindowResolution};
 
 mod grid;
+mod biome;
+mod nodes;
 mod crop;
 mod mouse;
 mod world;
@@ -8,14 +10,17 @@ mod camera;
 mod player;
 mod gameui;
 mod worker;
+mod needs;
+mod economy;
+mod jobs;
 mod buildings;
-
-// not yet implemented
-// enum GameState {
-//     Game,
-//     Pause,
-//     MenuMyPlayerPlugin
-// }
+mod pathfinding;
+mod gamestate;
+mod lighting;
+mod market;
+mod storage;
+mod production;
+mod upkeep;
 
 fn main() {
     let mut app: App = App::new();
@@ -32,7 +37,8 @@ fn main() {
         ..default()
     }));
 
-    // app.add_plugins(grid::MyGridPlugin);
+    app.add_plugins(grid::MyGridPlugin);
+    app.add_plugins(nodes::MyResourceNodePlugin);
     app.add_plugins(crop::MyCropPlugin);
     app.add_plugins(mouse::MyMousePlugin);
     app.add_plugins(world::MyWorldPlugin);
@@ -40,7 +46,17 @@ fn main() {
     app.add_plugins(camera::MyCameraPlugin);
     app.add_plugins(gameui::MyGameUiPlugin);
     app.add_plugins(worker::MyWorkerPlugin);
+    app.add_plugins(needs::MyNeedsPlugin);
+    app.add_plugins(economy::MyEconomyPlugin);
+    app.add_plugins(jobs::MyJobPlugin);
     app.add_plugins(buildings::MyBuildingPlugin);
+    app.add_plugins(pathfinding::MyPathfindingPlugin);
+    app.add_plugins(gamestate::MyGameStatePlugin);
+    app.add_plugins(lighting::MyLightingPlugin);
+    app.add_plugins(market::MyMarketPlugin);
+    app.add_plugins(storage::MyStoragePlugin);
+    app.add_plugins(production::MyProductionPlugin);
+    app.add_plugins(upkeep::MyUpkeepPlugin);
     
     app.run();
 }
\ No newline at end of file
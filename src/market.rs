@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{buildings::ConstructionSite, crop::{CropDefinition, CropRegistry}, economy::{LedgerCategory, TreasuryAccess}, gamestate::GameState, player::{ItemType, PlayerInventory}, storage::{Storage, TownStock}, worker::GoodsDelivered, world::DayStarted};
+
+// coins for one item when the market has its usual stock, items without a price can't be traded
+const BASE_PRICES: [(ItemType, i32); 14] = [
+    (ItemType::House, 15),
+    (ItemType::Market, 30),
+    (ItemType::Warehouse, 20),
+    (ItemType::Kitchen, 25),
+    (ItemType::Mill, 30),
+    (ItemType::Dirt, 2),
+    (ItemType::Potato, 4),
+    (ItemType::Berries, 2),
+    (ItemType::Wood, 3),
+    (ItemType::Stone, 3),
+    (ItemType::Reeds, 1),
+    (ItemType::Meal, 8),
+    (ItemType::Wheat, 2),
+    (ItemType::Flour, 6)
+];
+const SELL_IMPACT: f32 = 0.03; // every sold item lowers the price by this share
+const BUY_IMPACT: f32 = 0.05; // every bought item raises it by this share
+const MIN_PRICE_FACTOR: f32 = 0.25;
+const MAX_PRICE_FACTOR: f32 = 4.0;
+const DAILY_RECOVERY: f32 = 0.3; // share of the way back to the base price made every day
+const SELL_RESERVE: i32 = 20; // delivered goods are sold only above this stock, the town eats the rest
+
+// the market building, goods are only sold while one is built
+#[derive(Debug, Component)]
+pub struct MarketBuilding;
+
+// current price of every tradeable item as a factor of its base price
+// items from the data files (crops...) are added to the built-in ones when they are loaded
+#[derive(Debug, Resource)]
+pub struct MarketPrices {
+    base: Vec<(ItemType, i32)>,
+    factors: HashMap<ItemType, f32>
+}
+
+impl Default for MarketPrices {
+    fn default() -> Self {
+        MarketPrices { base: BASE_PRICES.to_vec(), factors: BASE_PRICES.iter().map(|(item, _)| (*item, 1.0)).collect() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeError {
+    NotTraded,
+    CannotAfford(i32), // the cost
+    NoRoom // the player inventory is full
+}
+
+impl std::fmt::Display for TradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TradeError::NotTraded => write!(f, "not traded"),
+            TradeError::CannotAfford(cost) => write!(f, "costs $ {}", cost),
+            TradeError::NoRoom => write!(f, "inventory full")
+        }
+    }
+}
+
+fn unit_price(base: i32, factor: f32) -> i32 {
+    ((base as f32 * factor).round() as i32).max(1)
+}
+
+impl MarketPrices {
+    pub fn tradeable_items(&self) -> impl Iterator<Item = ItemType> + '_ {
+        self.base.iter().map(|(item, _)| *item)
+    }
+
+    fn base_price(&self, item: ItemType) -> Option<i32> {
+        self.base.iter().find(|(i, _)| *i == item).map(|(_, price)| *price)
+    }
+
+    // built-in prices are kept, a known item only gets its new base price
+    pub fn set_base_price(&mut self, item: ItemType, price: i32) {
+        if BASE_PRICES.iter().any(|(i, _)| *i == item) { return; }
+
+        match self.base.iter_mut().find(|(i, _)| *i == item) {
+            Some(known) => known.1 = price,
+            None => {
+                self.base.push((item, price));
+                self.factors.insert(item, 1.0);
+            }
+        }
+    }
+
+    // price of the next single item
+    pub fn price(&self, item: ItemType) -> Option<i32> {
+        Some(unit_price(self.base_price(item)?, *self.factors.get(&item)?))
+    }
+
+    // walks the price item by item, returns the total and the factor after the trade
+    fn quote(&self, item: ItemType, amount: i32, impact: f32) -> Option<(i32, f32)> {
+        let base = self.base_price(item)?;
+        let mut factor = *self.factors.get(&item)?;
+        let mut total = 0;
+
+        for _ in 0..amount {
+            total += unit_price(base, factor);
+            factor = (factor * (1. + impact)).clamp(MIN_PRICE_FACTOR, MAX_PRICE_FACTOR);
+        }
+
+        Some((total, factor))
+    }
+
+    // pays from the treasury and pushes the price up, returns the cost
+    pub fn buy(&mut self, item: ItemType, amount: i32, funds: &mut TreasuryAccess) -> Result<i32, TradeError> {
+        let (cost, factor) = self.quote(item, amount, BUY_IMPACT).ok_or(TradeError::NotTraded)?;
+        if !funds.spend(cost, LedgerCategory::Purchases) { return Err(TradeError::CannotAfford(cost)); }
+        self.factors.insert(item, factor);
+        Ok(cost)
+    }
+
+    // pays into the treasury and floods the price down, returns the coins earned
+    pub fn sell(&mut self, item: ItemType, amount: i32, funds: &mut TreasuryAccess) -> Result<i32, TradeError> {
+        let (coins, factor) = self.quote(item, amount, -SELL_IMPACT).ok_or(TradeError::NotTraded)?;
+        funds.earn(coins, LedgerCategory::Sales);
+        self.factors.insert(item, factor);
+        Ok(coins)
+    }
+
+    fn recover(&mut self) {
+        for factor in self.factors.values_mut() {
+            *factor += (1. - *factor) * DAILY_RECOVERY;
+        }
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct GoodsBought {
+    pub item: ItemType,
+    pub amount: i32,
+    pub coins: i32
+}
+
+#[derive(Debug, Event)]
+pub struct PurchaseRejected {
+    pub item: ItemType,
+    pub reason: TradeError
+}
+
+#[derive(Debug, Event)]
+pub struct GoodsSold {
+    pub item: ItemType,
+    pub amount: i32,
+    pub coins: i32
+}
+
+pub struct MyMarketPlugin;
+
+impl Plugin for MyMarketPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MarketPrices>();
+        app.add_event::<GoodsBought>();
+        app.add_event::<PurchaseRejected>();
+        app.add_event::<GoodsSold>();
+
+        app.add_systems(Update, (sell_delivered_goods, recover_prices).run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, add_crop_prices);
+        app.add_systems(Update, log_market_prices);
+    }
+}
+
+// the goods the town owns, in the storages and the player inventory
+#[derive(SystemParam)]
+struct TownGoods<'w, 's> {
+    stock: Res<'w, TownStock>,
+    inventory: ResMut<'w, PlayerInventory>,
+    storages: Query<'w, 's, &'static mut Storage>
+}
+
+impl TownGoods<'_, '_> {
+    fn count(&self, item: ItemType) -> i32 {
+        self.stock.count(item) + self.inventory.count(item)
+    }
+
+    // from the storage the goods were delivered to, the player inventory without one
+    fn take(&mut self, item: ItemType, amount: i32, storage: Option<Entity>) -> bool {
+        match storage {
+            Some(entity) => self.storages.get_mut(entity).is_ok_and(|mut storage| storage.take(item, amount)),
+            None => self.inventory.take(item, amount)
+        }
+    }
+}
+
+// with a market built, delivered goods above the town reserve become income
+// they are taken back from wherever they were delivered
+fn sell_delivered_goods(
+    mut delivered: EventReader<GoodsDelivered>,
+    mut prices: ResMut<MarketPrices>,
+    mut funds: TreasuryAccess,
+    mut goods: TownGoods,
+    mut sold: EventWriter<GoodsSold>,
+    markets: Query<(), (With<MarketBuilding>, Without<ConstructionSite>)>
+) {
+    if markets.is_empty() { delivered.clear(); return; }
+
+    for delivery in delivered.read() {
+        let surplus = delivery.amount.min(goods.count(delivery.item) - SELL_RESERVE);
+        if surplus <= 0 || prices.price(delivery.item).is_none() { continue; }
+        if !goods.take(delivery.item, surplus, delivery.storage) { continue; }
+
+        if let Ok(coins) = prices.sell(delivery.item, surplus, &mut funds) {
+            sold.write(GoodsSold { item: delivery.item, amount: surplus, coins });
+        }
+    }
+}
+
+// buys into the player inventory with treasury coins, nothing is bought if it doesn't fit
+pub fn buy_into_inventory(
+    item: ItemType,
+    amount: i32,
+    prices: &mut MarketPrices,
+    funds: &mut TreasuryAccess,
+    inventory: &mut PlayerInventory
+) -> Result<i32, TradeError> {
+    if inventory.room_for(item) < amount { return Err(TradeError::NoRoom); }
+
+    let coins = prices.buy(item, amount, funds)?;
+    inventory.add(item, amount);
+    Ok(coins)
+}
+
+// crops that come with a price can be bought as seeds and their harvest sold
+fn add_crop_prices(registry: Res<CropRegistry>, definitions: Res<Assets<CropDefinition>>, mut prices: ResMut<MarketPrices>) {
+    if !registry.is_changed() { return; }
+
+    for def in registry.crops.iter().filter_map(|id| definitions.get(*id)) {
+        let Some(price) = def.price else { continue; };
+        prices.set_base_price(def.yield_item, price);
+        prices.set_base_price(def.seed_item, price);
+    }
+}
+
+fn recover_prices(mut day_started: EventReader<DayStarted>, mut prices: ResMut<MarketPrices>) {
+    for _ in day_started.read() { prices.recover(); }
+}
+
+fn log_market_prices(input: Res<ButtonInput<KeyCode>>, prices: Res<MarketPrices>) {
+    if input.just_pressed(KeyCode::KeyM) {
+        for item in prices.tradeable_items() {
+            info!("[market] {:?} $ {}", item, prices.price(item).unwrap_or(0));
+        }
+    }
+}
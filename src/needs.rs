@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::{buildings::ConstructionSite, gamestate::GameState, storage::{take_from_storages, Storage}, player::{ItemType, PlayerInventory}, world::{HourChanged, WorldSettings}, worker::{is_work_time, WorkerData, WorkerState}};
+
+const MAX_NEED: f32 = 100.;
+const HUNGER_PER_HOUR: f32 = 4.;
+const ENERGY_PER_HOUR: f32 = 3.;
+const SLEEP_ENERGY_PER_HOUR: f32 = 12.;
+const ROUGH_SLEEP_ENERGY_PER_HOUR: f32 = 6.; // homeless workers rest outside at night, slower than in a house
+const EAT_BELOW_HUNGER: f32 = 60.;
+const LOW_NEED: f32 = 30.; // below this the worker gets slower
+
+// food the workers can eat and how much hunger it restores
+const FOOD: [(ItemType, f32); 3] = [
+    (ItemType::Meal, 60.),
+    (ItemType::Potato, 35.),
+    (ItemType::Berries, 20.)
+];
+
+// 100 is fully fed / rested, 0 is starving / exhausted
+#[derive(Debug, Component)]
+pub struct WorkerNeeds {
+    pub hunger: f32,
+    pub energy: f32
+}
+
+impl Default for WorkerNeeds {
+    fn default() -> Self {
+        WorkerNeeds { hunger: MAX_NEED, energy: MAX_NEED }
+    }
+}
+
+impl WorkerNeeds {
+    // 1.0 while the needs are fine, down to 0.5 when the lowest one gets to 0
+    pub fn work_speed(&self) -> f32 {
+        let lowest = self.hunger.min(self.energy);
+        if lowest >= LOW_NEED { return 1.0; }
+        0.5 + 0.5 * (lowest / LOW_NEED)
+    }
+}
+
+pub struct MyNeedsPlugin;
+
+impl Plugin for MyNeedsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, needs_hourly_update.run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, log_worker_needs);
+    }
+}
+
+// every in-game hour the needs go down, sleeping restores energy and eating restores hunger
+fn needs_hourly_update(
+    day: Res<WorldSettings>,
+    mut hour_changed: EventReader<HourChanged>,
+    mut inventory: ResMut<PlayerInventory>,
+    mut storages: Query<&mut Storage, Without<ConstructionSite>>,
+    mut workers: Query<(&mut WorkerNeeds, &WorkerState, &WorkerData)>
+) {
+    for _ in hour_changed.read() {
+        for (mut needs, state, worker_data) in &mut workers {
+            needs.hunger = (needs.hunger - HUNGER_PER_HOUR).max(0.);
+
+            // idle workers without a house stay where they are at night
+            let sleeping_rough = *state == WorkerState::Idle && worker_data.house.is_none() && !is_work_time(&day);
+
+            needs.energy = match state {
+                WorkerState::Sleeping => (needs.energy + SLEEP_ENERGY_PER_HOUR).min(MAX_NEED),
+                _ if sleeping_rough => (needs.energy + ROUGH_SLEEP_ENERGY_PER_HOUR).min(MAX_NEED),
+                _ => (needs.energy - ENERGY_PER_HOUR).max(0.)
+            };
+
+            // workers eat while resting, from the town storages first
+            if (*state == WorkerState::Sleeping || sleeping_rough) && needs.hunger < EAT_BELOW_HUNGER
+                && let Some((_, restores)) = FOOD.iter().find(|(food, _)| take_from_storages(storages.iter_mut(), *food, 1) || inventory.take(*food, 1)) {
+                needs.hunger = (needs.hunger + restores).min(MAX_NEED);
+            }
+        }
+    }
+}
+
+fn log_worker_needs(input: Res<ButtonInput<KeyCode>>, workers: Query<(&WorkerNeeds, &WorkerState, Entity), With<WorkerData>>) {
+    if input.just_pressed(KeyCode::KeyN) {
+        for (needs, state, entity) in &workers {
+            info!("[worker_needs] {} {:?} {:?}", entity, state, needs);
+        }
+    }
+}
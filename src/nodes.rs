@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{biome::Biome, gamestate::GameState, worker::{worker_working, CarriedInventory}, buildings::{ConstructionSite, HouseData}, grid::{world_to_tile, SquareType, TerrainMap}, jobs::{JobBoard, JobCompleted, JobKind, Skill}, player::ItemType};
+
+const GATHER_RADIUS: i32 = 6; // nodes this close to a house get a gather job
+
+// trees, rocks, reeds and berry bushes scattered by the chunk generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Tree,
+    Rock,
+    Reeds,
+    BerryBush
+}
+
+#[derive(Debug, Component)]
+pub struct ResourceNode {
+    pub kind: NodeKind,
+    pub tile: (i32,i32)
+}
+
+// send it when a worker finished gathering a node
+#[derive(Debug, Event)]
+pub struct NodeHarvested {
+    pub node: Entity
+}
+
+impl NodeKind {
+    pub fn yield_item(&self) -> ItemType {
+        match self {
+            NodeKind::Tree => ItemType::Wood,
+            NodeKind::Rock => ItemType::Stone,
+            NodeKind::Reeds => ItemType::Reeds,
+            NodeKind::BerryBush => ItemType::Berries
+        }
+    }
+
+    pub fn sprite(&self) -> Sprite {
+        let (color, size) = match self {
+            NodeKind::Tree => (Color::srgb(0.1, 0.35, 0.12), 0.8),
+            NodeKind::Rock => (Color::srgb(0.5, 0.5, 0.52), 0.6),
+            NodeKind::Reeds => (Color::srgb(0.55, 0.6, 0.3), 0.5),
+            NodeKind::BerryBush => (Color::srgb(0.6, 0.15, 0.3), 0.6)
+        };
+        Sprite { color, custom_size: Some(Vec2::splat(size)), ..default() }
+    }
+}
+
+// which node (if any) grows on a tile, `roll` is a deterministic random value from 0.0 to 1.0
+pub fn node_for_tile(square: SquareType, biome: Biome, near_water: bool, roll: f32) -> Option<NodeKind> {
+    match square {
+        SquareType::DarkGrass => {
+            let tree_chance = if biome == Biome::Forest { 0.25 } else { 0.12 };
+            (roll < tree_chance).then_some(NodeKind::Tree)
+        },
+        SquareType::Rock => (roll < 0.1).then_some(NodeKind::Rock),
+        SquareType::Sand | SquareType::Grass if near_water => (roll < 0.15).then_some(NodeKind::Reeds),
+        SquareType::Grass if matches!(biome, Biome::Forest | Biome::Plains) => (roll < 0.03).then_some(NodeKind::BerryBush),
+        _ => Option::None
+    }
+}
+
+pub struct MyResourceNodePlugin;
+
+impl Plugin for MyResourceNodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NodeHarvested>();
+        app.add_systems(Update, (post_gather_jobs, gather_job_results.after(worker_working), remove_harvested_nodes).chain().run_if(in_state(GameState::Playing)));
+    }
+}
+
+fn post_gather_jobs(
+    mut board: ResMut<JobBoard>,
+    houses: Query<&Transform, (With<HouseData>, Without<ConstructionSite>)>,
+    nodes: Query<(&ResourceNode, Entity)>
+) {
+    let house_tiles: Vec<(i32,i32)> = houses.iter().map(|tf| world_to_tile(tf.translation.truncate())).collect();
+    if house_tiles.is_empty() { return; }
+
+    for (node, entity) in &nodes {
+        if board.has_job(JobKind::Harvest, entity) { continue; }
+
+        let near_house = house_tiles.iter().any(|h| (h.0 - node.tile.0).abs() <= GATHER_RADIUS && (h.1 - node.tile.1).abs() <= GATHER_RADIUS);
+        if near_house { board.post(JobKind::Harvest, entity, node.tile, 0, Skill::Gathering); }
+    }
+}
+
+fn gather_job_results(
+    mut completed: EventReader<JobCompleted>,
+    mut harvested: EventWriter<NodeHarvested>,
+    mut workers: Query<&mut CarriedInventory>,
+    nodes: Query<&ResourceNode>
+) {
+    for done in completed.read().filter(|c| c.job.kind == JobKind::Harvest) {
+        let Ok(node) = nodes.get(done.job.target) else { continue; };
+        // the worker delivers it to a storage like a crop harvest
+        if let Ok(mut carried) = workers.get_mut(done.worker) { carried.add(node.kind.yield_item(), 1); }
+        harvested.write(NodeHarvested { node: done.job.target });
+    }
+}
+
+// harvested nodes are remembered in the chunk edits so they don't grow back when the chunk reloads
+fn remove_harvested_nodes(
+    mut cmm: Commands,
+    mut harvested: EventReader<NodeHarvested>,
+    mut terrain: ResMut<TerrainMap>,
+    nodes: Query<&ResourceNode>
+) {
+    for event in harvested.read() {
+        let Ok(node) = nodes.get(event.node) else { continue; };
+        terrain.mark_node_harvested(node.tile);
+        cmm.entity(event.node).despawn();
+    }
+}
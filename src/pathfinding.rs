@@ -0,0 +1,143 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet, VecDeque}};
+
+use bevy::prelude::*;
+
+use crate::{buildings::{BuildingCoords, BuildingPlaced}, grid::{world_to_tile, TerrainMap}, world::DayStarted};
+
+// stops the search on unreachable goals, the world has no borders
+const MAX_SEARCHED_TILES: usize = 4096;
+const NEIGHBOURS: [(i32,i32); 4] = [(1,0),(-1,0),(0,1),(0,-1)];
+
+// tiles the worker still has to walk through, the last one is the goal
+#[derive(Debug, Component, Default)]
+pub struct WorkerPath {
+    pub goal: Option<(i32,i32)>,
+    pub waypoints: VecDeque<(i32,i32)>,
+    pub blocked: bool, // no path found to the goal, not searched again until the goal changes
+    pub unreachable: HashSet<(i32,i32)> // blocked goals the worker gave up on, forgotten every day
+}
+
+impl WorkerPath {
+    pub fn clear(&mut self) {
+        self.goal = Option::None;
+        self.waypoints.clear();
+        self.blocked = false;
+    }
+
+    // remembers the blocked goal so it isn't picked again, and stops walking
+    pub fn give_up(&mut self) {
+        if let Some(goal) = self.goal { self.unreachable.insert(goal); }
+        self.clear();
+    }
+}
+
+pub struct MyPathfindingPlugin;
+
+impl Plugin for MyPathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (invalidate_blocked_paths, forget_unreachable_goals));
+    }
+}
+
+// water can't be walked and buildings can only be walked into when they are the goal
+pub fn is_passable(terrain: &TerrainMap, buildings: &BuildingCoords, tile: (i32,i32), goal: (i32,i32)) -> bool {
+    tile == goal || (terrain.is_walkable(tile) && !buildings.data.contains(&tile))
+}
+
+// A* over the tile grid, the returned path doesn't include the start tile
+pub fn find_path(start: (i32,i32), goal: (i32,i32), passable: impl Fn((i32,i32)) -> bool) -> Option<Vec<(i32,i32)>> {
+    let heuristic = |tile: (i32,i32)| (tile.0 - goal.0).abs() + (tile.1 - goal.1).abs();
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<(i32,i32), (i32,i32)> = HashMap::new();
+    let mut cost: HashMap<(i32,i32), i32> = HashMap::new();
+
+    open.push(Reverse((heuristic(start), 0, start)));
+    cost.insert(start, 0);
+
+    while let Some(Reverse((_, tile_cost, tile))) = open.pop() {
+        if tile == goal {
+            let mut path = vec![tile];
+            let mut current = tile;
+            while let Some(&previous) = came_from.get(&current) {
+                if previous == start { break; }
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        if tile_cost > cost[&tile] { continue; } // already reached with a lower cost
+        if cost.len() > MAX_SEARCHED_TILES { return Option::None; }
+
+        for (dx, dy) in NEIGHBOURS {
+            let next = (tile.0 + dx, tile.1 + dy);
+            if !passable(next) { continue; }
+
+            let next_cost = tile_cost + 1;
+            if cost.get(&next).is_none_or(|&c| next_cost < c) {
+                cost.insert(next, next_cost);
+                came_from.insert(next, tile);
+                open.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+            }
+        }
+    }
+
+    Option::None
+}
+
+// moves the worker one frame along its path to `goal`, the path is searched again when the goal changes
+// returns true when the worker is standing on the goal tile
+pub fn walk_to(
+    goal: (i32,i32),
+    tf: &mut Transform,
+    path: &mut WorkerPath,
+    step: f32,
+    passable: impl Fn((i32,i32)) -> bool
+) -> bool {
+    let actual_tile = world_to_tile(tf.translation.truncate());
+
+    if path.goal != Some(goal) {
+        path.clear();
+        path.goal = Some(goal);
+        match find_path(actual_tile, goal, passable) {
+            Some(waypoints) => { path.waypoints = waypoints.into(); },
+            None => { path.blocked = true; }
+        }
+    }
+
+    let Some(&next) = path.waypoints.front() else { return actual_tile == goal && !path.blocked; };
+
+    let target = Vec3::new(next.0 as f32, next.1 as f32, tf.translation.z);
+    let to_target = target - tf.translation;
+
+    if to_target.length() <= step {
+        tf.translation = target;
+        path.waypoints.pop_front();
+    } else {
+        tf.translation += to_target.normalize() * step;
+    }
+
+    false
+}
+
+// a new building on the way makes the worker search a new path
+fn invalidate_blocked_paths(mut placed: EventReader<BuildingPlaced>, mut paths: Query<&mut WorkerPath>) {
+    for building in placed.read() {
+        for mut path in &mut paths {
+            if path.goal != Some(building.tile) && path.waypoints.contains(&building.tile) {
+                path.clear();
+            }
+        }
+    }
+}
+
+// the terrain and the buildings may have changed, the worker tries the goals again
+fn forget_unreachable_goals(mut day_started: EventReader<DayStarted>, mut paths: Query<&mut WorkerPath>) {
+    if day_started.read().count() == 0 { return; }
+
+    for mut path in &mut paths {
+        path.unreachable.clear();
+    }
+}
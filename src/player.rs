@@ -1,8 +1,10 @@
+use std::{fmt, sync::Mutex};
+
 use bevy::{platform::collections::HashSet, prelude::*};
 
 use crate::gameui::ItemSelected;
 
-pub const INVENTORYSIZE: i32 = 6;
+pub const INVENTORYSIZE: i32 = 8;
 const MAXSTACKSIZE: i32 = 999;
 
 #[derive(Debug, Component)]
@@ -10,14 +12,86 @@ pub enum Tool {
     Shovel
 }
 
-#[derive(Debug, Component, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Component, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum ItemType {
     None,
     Coin,
     House,
+    Market,
+    Warehouse,
+    Kitchen,
+    Mill,
     Shovel,
     Dirt,
-    Worker // testing
+    Worker, // testing
+    Potato,
+    Wood,
+    Stone,
+    Reeds,
+    Berries,
+    Meal,
+    Wheat,
+    Flour,
+    Produce(ProduceName) // items that only exist in the data files (crops...)
+}
+
+// names used in the data files and shown in the ui
+const ITEM_NAMES: [(ItemType, &str); 18] = [
+    (ItemType::None, "None"),
+    (ItemType::Coin, "Coin"),
+    (ItemType::House, "House"),
+    (ItemType::Market, "Market"),
+    (ItemType::Warehouse, "Warehouse"),
+    (ItemType::Kitchen, "Kitchen"),
+    (ItemType::Mill, "Mill"),
+    (ItemType::Shovel, "Shovel"),
+    (ItemType::Dirt, "Dirt"),
+    (ItemType::Worker, "Worker"),
+    (ItemType::Potato, "Potato"),
+    (ItemType::Wood, "Wood"),
+    (ItemType::Stone, "Stone"),
+    (ItemType::Reeds, "Reeds"),
+    (ItemType::Berries, "Berries"),
+    (ItemType::Meal, "Meal"),
+    (ItemType::Wheat, "Wheat"),
+    (ItemType::Flour, "Flour")
+];
+
+impl ItemType {
+    // a built-in item by its name, any other name is a produce item
+    pub fn from_name(name: &str) -> ItemType {
+        ITEM_NAMES.iter().find(|(_, n)| *n == name).map(|(item, _)| *item).unwrap_or_else(|| ItemType::Produce(ProduceName::intern(name)))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemType::Produce(produce) => produce.0,
+            item => ITEM_NAMES.iter().find(|(i, _)| i == item).map_or("?", |(_, n)| n)
+        }
+    }
+}
+
+impl fmt::Debug for ItemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// interned so ItemType stays Copy, every name is stored once however often it is loaded
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProduceName(&'static str);
+
+impl ProduceName {
+    fn intern(name: &str) -> ProduceName {
+        static NAMES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+        let mut names = NAMES.lock().unwrap();
+        if let Some(known) = names.iter().find(|n| **n == name) { return ProduceName(known); }
+
+        let leaked: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        names.push(leaked);
+        ProduceName(leaked)
+    }
 }
 
 #[derive(Debug, Resource)]
@@ -40,6 +114,45 @@ pub struct PlayerInventory {
     pub size: i32
 }
 
+impl PlayerInventory {
+    pub fn count(&self, item: ItemType) -> i32 {
+        self.items.iter().filter(|i| i.item == item).map(|i| i.total_amount).sum()
+    }
+
+    // removes `amount` items from the first stack that has enough of them
+    pub fn take(&mut self, item: ItemType, amount: i32) -> bool {
+        match self.items.iter_mut().find(|i| i.item == item && i.total_amount >= amount) {
+            Some(stack) => { stack.total_amount -= amount; true },
+            None => false
+        }
+    }
+
+    // how many can be added before the inventory is full
+    pub fn room_for(&self, item: ItemType) -> i32 {
+        let in_stacks: i32 = self.items.iter().filter(|i| i.item == item).map(|i| i.max_amount - i.total_amount).sum();
+        in_stacks + (self.size - self.items.len() as i32).max(0) * MAXSTACKSIZE
+    }
+
+    // merges into the stacks that have room and opens new stacks while there are free slots
+    // returns the amount that didn't fit
+    pub fn add(&mut self, item: ItemType, mut amount: i32) -> i32 {
+        for stack in self.items.iter_mut().filter(|i| i.item == item && i.total_amount < i.max_amount) {
+            let moved = amount.min(stack.max_amount - stack.total_amount);
+            stack.total_amount += moved;
+            amount -= moved;
+            if amount == 0 { return 0; }
+        }
+
+        while amount > 0 && (self.items.len() as i32) < self.size {
+            let moved = amount.min(MAXSTACKSIZE);
+            self.items.push(ItemStack { item, total_amount: moved, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
+            amount -= moved;
+        }
+
+        amount
+    }
+}
+
 pub struct MyPlayerPlugin;
 
 impl Plugin for MyPlayerPlugin {
@@ -56,6 +169,9 @@ fn init(mut inventory: ResMut<PlayerInventory>) {
     inventory.items.push(ItemStack { item: ItemType::Coin, total_amount: 64, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
     inventory.items.push(ItemStack { item: ItemType::House, total_amount: 32, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
     inventory.items.push(ItemStack { item: ItemType::Dirt, total_amount: 16, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
+    inventory.items.push(ItemStack { item: ItemType::Potato, total_amount: 24, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
+    inventory.items.push(ItemStack { item: ItemType::Market, total_amount: 1, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
+    inventory.items.push(ItemStack { item: ItemType::Warehouse, total_amount: 1, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
     inventory.items.push(ItemStack { item: ItemType::Worker, total_amount: 999, max_amount: MAXSTACKSIZE, assigned: false, ui_entity: Entity::from_raw(0) });
 }
 
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+
+use crate::{buildings::ConstructionSite, gamestate::GameState, grid::world_to_tile, jobs::{JobBoard, JobCompleted, JobKind, Skill}, player::{ItemType, PlayerInventory}, storage::Storage, worker::{worker_working, CarriedInventory, GoodsDelivered, WorkTarget, WorkerData, WorkerState}, world::{HourChanged, WorldSettings}};
+
+const INPUT_CAPACITY: i32 = 20;
+const HAUL_BATCHES: i32 = 2; // a haul brings the inputs of this many batches
+
+// what a processing building makes, one batch takes `hours` in-game hours of staffed work
+pub struct Recipe {
+    pub building: ItemType,
+    pub inputs: &'static [(ItemType, i32)],
+    pub outputs: &'static [(ItemType, i32)],
+    pub hours: f32,
+    pub open_hours: (u32, u32) // from, until
+}
+
+impl Recipe {
+    pub fn is_open(&self, hour: u32) -> bool {
+        (self.open_hours.0..self.open_hours.1).contains(&hour)
+    }
+}
+
+pub const RECIPES: [Recipe; 2] = [
+    Recipe { building: ItemType::Kitchen, inputs: &[(ItemType::Potato, 2)], outputs: &[(ItemType::Meal, 1)], hours: 2., open_hours: (8, 20) },
+    Recipe { building: ItemType::Mill, inputs: &[(ItemType::Wheat, 3)], outputs: &[(ItemType::Flour, 2)], hours: 3., open_hours: (9, 18) }
+];
+
+pub fn recipe_for(building: ItemType) -> Option<&'static Recipe> {
+    RECIPES.iter().find(|r| r.building == building)
+}
+
+// a built kitchen, mill... the inputs of a batch are loaded when a worker starts it
+// inputs are hauled in from the storages, it is not a storage itself
+#[derive(Debug, Component)]
+pub struct ProductionBuilding {
+    pub building: ItemType,
+    pub inputs: Storage,
+    pub loaded: bool,
+    pub progress_hours: f32
+}
+
+impl ProductionBuilding {
+    pub fn new(building: ItemType) -> Self {
+        ProductionBuilding { building, inputs: Storage::new(INPUT_CAPACITY), loaded: false, progress_hours: 0. }
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct BatchProduced {
+    pub building: Entity,
+    pub outputs: &'static [(ItemType, i32)]
+}
+
+pub struct MyProductionPlugin;
+
+impl Plugin for MyProductionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BatchProduced>();
+        app.add_systems(Update, (post_operate_jobs, post_haul_jobs, haul_job_results.after(worker_working), receive_hauled_goods, production_hourly).chain().run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, log_batches_produced);
+    }
+}
+
+// open buildings want a worker, closing sends the worker away
+fn post_operate_jobs(
+    day: Res<WorldSettings>,
+    mut board: ResMut<JobBoard>,
+    buildings: Query<(&ProductionBuilding, &Transform, Entity), Without<ConstructionSite>>
+) {
+    for (production, tf, entity) in &buildings {
+        let Some(recipe) = recipe_for(production.building) else { continue; };
+
+        if recipe.is_open(day.hour) {
+            board.post(JobKind::Operate, entity, world_to_tile(tf.translation.truncate()), 1, Skill::Crafting);
+        } else {
+            board.remove(JobKind::Operate, entity);
+        }
+    }
+}
+
+// a building short of inputs asks for them from a storage that has them, the job tile is where they are picked up
+fn post_haul_jobs(
+    mut board: ResMut<JobBoard>,
+    storages: Query<(&Storage, &Transform), Without<ConstructionSite>>,
+    buildings: Query<(&ProductionBuilding, Entity), Without<ConstructionSite>>
+) {
+    for (production, entity) in &buildings {
+        if board.has_job(JobKind::Haul, entity) { continue; }
+        let Some(recipe) = recipe_for(production.building) else { continue; };
+
+        let source = recipe.inputs.iter()
+            .filter(|(item, amount)| production.inputs.count(*item) < *amount)
+            .find_map(|(item, _)| storages.iter().filter(|(storage, _)| storage.count(*item) > 0).max_by_key(|(storage, _)| storage.count(*item)));
+
+        if let Some((_, tf)) = source {
+            board.post(JobKind::Haul, entity, world_to_tile(tf.translation.truncate()), 1, Skill::Hauling);
+        }
+    }
+}
+
+// the worker picks up what the building is missing and carries it there
+fn haul_job_results(
+    mut completed: EventReader<JobCompleted>,
+    mut storages: Query<(&mut Storage, &Transform), Without<ConstructionSite>>,
+    buildings: Query<(&ProductionBuilding, &Transform), Without<ConstructionSite>>,
+    mut workers: Query<&mut CarriedInventory>
+) {
+    for done in completed.read().filter(|c| c.job.kind == JobKind::Haul) {
+        let Ok((production, building_tf)) = buildings.get(done.job.target) else { continue; };
+        let Some(recipe) = recipe_for(production.building) else { continue; };
+        let Some((mut storage, _)) = storages.iter_mut().find(|(_, tf)| world_to_tile(tf.translation.truncate()) == done.job.tile) else { continue; };
+        let Ok(mut carried) = workers.get_mut(done.worker) else { continue; };
+
+        // never more than the building inputs can hold
+        let mut space = production.inputs.space();
+        for (item, amount) in recipe.inputs {
+            let wanted = (amount * HAUL_BATCHES - production.inputs.count(*item)).min(storage.count(*item)).min(space);
+            if wanted > 0 && storage.take(*item, wanted) {
+                carried.add(*item, wanted);
+                space -= wanted;
+            }
+        }
+
+        if !carried.is_empty() {
+            carried.destination = Some(WorkTarget { entity: done.job.target, tile: world_to_tile(building_tf.translation.truncate()) });
+        }
+    }
+}
+
+// what doesn't fit goes back to the worker hands and is delivered to a storage
+fn receive_hauled_goods(mut delivered: EventReader<GoodsDelivered>, mut buildings: Query<&mut ProductionBuilding>, mut workers: Query<&mut CarriedInventory>) {
+    for delivery in delivered.read() {
+        let Some(mut production) = delivery.storage.and_then(|entity| buildings.get_mut(entity).ok()) else { continue; };
+        let left = production.inputs.add(delivery.item, delivery.amount);
+        if left == 0 { continue; }
+
+        match workers.get_mut(delivery.worker) {
+            Ok(mut carried) => carried.add(delivery.item, left),
+            Err(_) => warn!("[production] {:?} lost {} {:?}, inputs full", production.building, left, delivery.item)
+        }
+    }
+}
+
+// every hour a staffed building loads the inputs of a batch from its hauled inputs (the player inventory when short)
+// and works on it, a finished batch goes to the worker hands to be delivered and the job is done
+fn production_hourly(
+    mut hour_changed: EventReader<HourChanged>,
+    mut board: ResMut<JobBoard>,
+    mut inventory: ResMut<PlayerInventory>,
+    mut produced: EventWriter<BatchProduced>,
+    mut buildings: Query<(&mut ProductionBuilding, Entity), Without<ConstructionSite>>,
+    mut workers: Query<(&WorkerData, &WorkerState, &mut CarriedInventory)>
+) {
+    for _ in hour_changed.read() {
+        for (mut production, entity) in &mut buildings {
+            let Some(recipe) = recipe_for(production.building) else { continue; };
+            let Some(job) = board.claimed_job(JobKind::Operate, entity).copied() else { continue; };
+            let Some(worker) = job.claimed_by else { continue; };
+            let Ok((worker_data, state, mut carried)) = workers.get_mut(worker) else { continue; };
+            if *state != WorkerState::Working || worker_data.job.is_none_or(|j| j.id != job.id) { continue; }
+
+            if !production.loaded {
+                let available = recipe.inputs.iter().all(|(item, amount)| production.inputs.count(*item) + inventory.count(*item) >= *amount);
+                if !available { continue; }
+
+                for (item, amount) in recipe.inputs {
+                    let from_inputs = production.inputs.count(*item).min(*amount);
+                    if from_inputs > 0 { production.inputs.take(*item, from_inputs); }
+                    if from_inputs < *amount { inventory.take(*item, amount - from_inputs); }
+                }
+                production.loaded = true;
+            }
+
+            production.progress_hours += 1.;
+            if production.progress_hours < recipe.hours { continue; }
+
+            production.loaded = false;
+            production.progress_hours = 0.;
+            for (item, amount) in recipe.outputs { carried.add(*item, *amount); }
+            board.complete(job.id);
+            produced.write(BatchProduced { building: entity, outputs: recipe.outputs });
+        }
+    }
+}
+
+fn log_batches_produced(mut produced: EventReader<BatchProduced>) {
+    for batch in produced.read() {
+        debug!("[production] {} produced {:?}", batch.building, batch.outputs);
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{buildings::ConstructionSite, player::ItemType};
+
+pub const WAREHOUSE_CAPACITY: i32 = 100;
+
+// goods kept in a building, workers deliver here before the player inventory
+#[derive(Debug, Component)]
+pub struct Storage {
+    pub items: HashMap<ItemType, i32>,
+    pub capacity: i32 // items of any kind
+}
+
+impl Storage {
+    pub fn new(capacity: i32) -> Self {
+        Storage { items: HashMap::new(), capacity }
+    }
+
+    pub fn count(&self, item: ItemType) -> i32 {
+        self.items.get(&item).copied().unwrap_or(0)
+    }
+
+    pub fn used(&self) -> i32 {
+        self.items.values().sum()
+    }
+
+    pub fn space(&self) -> i32 {
+        (self.capacity - self.used()).max(0)
+    }
+
+    // returns the amount that didn't fit
+    pub fn add(&mut self, item: ItemType, amount: i32) -> i32 {
+        let stored = amount.min(self.space());
+        if stored > 0 { *self.items.entry(item).or_insert(0) += stored; }
+        amount - stored
+    }
+
+    // takes nothing if there are not enough
+    pub fn take(&mut self, item: ItemType, amount: i32) -> bool {
+        let Some(stored) = self.items.get_mut(&item).filter(|s| **s >= amount) else { return false; };
+        *stored -= amount;
+        if *stored == 0 { self.items.remove(&item); }
+        true
+    }
+}
+
+// takes from the first storage that has enough
+pub fn take_from_storages<'a>(storages: impl IntoIterator<Item = Mut<'a, Storage>>, item: ItemType, amount: i32) -> bool {
+    storages.into_iter().any(|mut storage| storage.take(item, amount))
+}
+
+// goods in every built storage, refreshed every frame
+#[derive(Debug, Resource, Default)]
+pub struct TownStock {
+    pub items: HashMap<ItemType, i32>,
+    pub capacity: i32
+}
+
+impl TownStock {
+    pub fn count(&self, item: ItemType) -> i32 {
+        self.items.get(&item).copied().unwrap_or(0)
+    }
+}
+
+pub struct MyStoragePlugin;
+
+impl Plugin for MyStoragePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TownStock>();
+        app.add_systems(Update, update_town_stock);
+    }
+}
+
+fn update_town_stock(mut stock: ResMut<TownStock>, storages: Query<&Storage, Without<ConstructionSite>>) {
+    let mut items: HashMap<ItemType, i32> = HashMap::new();
+    for storage in &storages {
+        for (item, amount) in &storage.items { *items.entry(*item).or_insert(0) += amount; }
+    }
+    let capacity = storages.iter().map(|s| s.capacity).sum();
+
+    if stock.items != items || stock.capacity != capacity {
+        stock.items = items;
+        stock.capacity = capacity;
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+
+use crate::{buildings::{construction_site_color, ConstructionSite, HouseData, PlacedBuilding}, economy::{LedgerCategory, TreasuryAccess}, gamestate::GameState, jobs::{JobBoard, JobKind}, player::ItemType, worker::WorkerData, world::HourChanged};
+
+// coins the treasury pays every day for each built building, buildings without upkeep are free
+const UPKEEP: [(ItemType, i32); 5] = [
+    (ItemType::House, 1),
+    (ItemType::Warehouse, 2),
+    (ItemType::Kitchen, 2),
+    (ItemType::Market, 3),
+    (ItemType::Mill, 3)
+];
+const RENT_PER_TENANT: i32 = 2; // paid by every worker living in a built house, from their own coins
+const DUES_HOUR: u32 = 6; // rent and upkeep are settled in the morning
+const GRACE_DAYS: u32 = 1; // unpaid days with only a warning, after that buildings start to fall apart
+
+pub fn upkeep_of(building: ItemType) -> i32 {
+    UPKEEP.iter().find(|(item, _)| *item == building).map(|(_, coins)| *coins).unwrap_or(0)
+}
+
+// days in a row the upkeep couldn't be fully paid, every day past the grace days one more building falls into disrepair
+#[derive(Debug, Resource, Default)]
+pub struct UpkeepArrears {
+    pub days: u32
+}
+
+#[derive(Debug, Event)]
+pub struct RentCollected {
+    pub tenants: i32,
+    pub total: i32
+}
+
+#[derive(Debug, Event)]
+pub struct UpkeepCharged {
+    pub paid: i32,
+    pub unpaid_buildings: i32
+}
+
+#[derive(Debug, Event)]
+pub struct UpkeepUnpaid {
+    pub days: u32,
+    pub unpaid_buildings: i32
+}
+
+// the building turned back into a construction site and has to be built again
+#[derive(Debug, Event)]
+pub struct BuildingDisrepair {
+    pub building: Entity,
+    pub item: ItemType
+}
+
+pub struct MyUpkeepPlugin;
+
+impl Plugin for MyUpkeepPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UpkeepArrears>();
+        app.add_event::<RentCollected>();
+        app.add_event::<UpkeepCharged>();
+        app.add_event::<UpkeepUnpaid>();
+        app.add_event::<BuildingDisrepair>();
+
+        // rent first so it can pay for the upkeep
+        app.add_systems(Update, (collect_rent, charge_upkeep, fall_into_disrepair).chain().run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, log_upkeep_events);
+    }
+}
+
+fn is_dues_hour(hour_changed: &mut EventReader<HourChanged>) -> bool {
+    hour_changed.read().any(|h| h.hour == DUES_HOUR)
+}
+
+// tenants that can't pay stay in the house, the town just doesn't get their rent
+fn collect_rent(
+    mut hour_changed: EventReader<HourChanged>,
+    mut funds: TreasuryAccess,
+    mut collected: EventWriter<RentCollected>,
+    houses: Query<&HouseData, Without<ConstructionSite>>,
+    mut workers: Query<&mut WorkerData>
+) {
+    if !is_dues_hour(&mut hour_changed) { return; }
+
+    let (mut tenants, mut total) = (0, 0);
+
+    for house in &houses {
+        for tenant in &house.assigned_workers {
+            let Ok(mut worker_data) = workers.get_mut(*tenant) else { continue; };
+            if worker_data.coins < RENT_PER_TENANT { continue; }
+
+            worker_data.coins -= RENT_PER_TENANT;
+            tenants += 1;
+            total += RENT_PER_TENANT;
+        }
+    }
+
+    if total > 0 { funds.earn(total, LedgerCategory::Rent); }
+    collected.write(RentCollected { tenants, total });
+}
+
+// cheap buildings are paid first, the most expensive unpaid ones are the first to fall into disrepair
+fn charge_upkeep(
+    mut hour_changed: EventReader<HourChanged>,
+    mut funds: TreasuryAccess,
+    mut arrears: ResMut<UpkeepArrears>,
+    mut charged: EventWriter<UpkeepCharged>,
+    mut unpaid_events: EventWriter<UpkeepUnpaid>,
+    mut disrepair: EventWriter<BuildingDisrepair>,
+    buildings: Query<(&PlacedBuilding, Entity), Without<ConstructionSite>>
+) {
+    if !is_dues_hour(&mut hour_changed) { return; }
+
+    let mut due: Vec<(ItemType, i32, Entity)> = buildings.iter()
+        .map(|(building, entity)| (building.0, upkeep_of(building.0), entity))
+        .filter(|(_, upkeep, _)| *upkeep > 0)
+        .collect();
+    due.sort_by_key(|(_, upkeep, _)| *upkeep);
+
+    let mut paid = 0;
+    let mut unpaid: Vec<(ItemType, Entity)> = Vec::new();
+
+    for (item, upkeep, entity) in due {
+        if funds.spend(upkeep, LedgerCategory::Upkeep) {
+            paid += upkeep;
+        } else {
+            unpaid.push((item, entity));
+        }
+    }
+
+    charged.write(UpkeepCharged { paid, unpaid_buildings: unpaid.len() as i32 });
+
+    if unpaid.is_empty() {
+        arrears.days = 0;
+        return;
+    }
+
+    arrears.days += 1;
+    unpaid_events.write(UpkeepUnpaid { days: arrears.days, unpaid_buildings: unpaid.len() as i32 });
+
+    let falling_apart = arrears.days.saturating_sub(GRACE_DAYS) as usize;
+    for (item, entity) in unpaid.into_iter().rev().take(falling_apart) {
+        disrepair.write(BuildingDisrepair { building: entity, item });
+    }
+}
+
+// the building turns back into a construction site, its tenants move out and nobody can work in it
+fn fall_into_disrepair(
+    mut cmm: Commands,
+    mut disrepair: EventReader<BuildingDisrepair>,
+    mut board: ResMut<JobBoard>,
+    mut buildings: Query<(&mut Sprite, Option<&mut HouseData>), Without<ConstructionSite>>,
+    mut workers: Query<&mut WorkerData>
+) {
+    for event in disrepair.read() {
+        let Ok((mut sprite, house)) = buildings.get_mut(event.building) else { continue; };
+
+        // tenants look for another house
+        if let Some(mut house) = house {
+            for tenant in house.assigned_workers.drain() {
+                if let Ok(mut worker_data) = workers.get_mut(tenant) { worker_data.house = Option::None; }
+            }
+        }
+
+        cmm.entity(event.building).insert(ConstructionSite { finished_color: sprite.color });
+        sprite.color = construction_site_color(sprite.color);
+        board.remove(JobKind::Operate, event.building);
+        board.remove(JobKind::Haul, event.building);
+    }
+}
+
+fn log_upkeep_events(mut rent: EventReader<RentCollected>, mut charged: EventReader<UpkeepCharged>, mut disrepair: EventReader<BuildingDisrepair>) {
+    for event in rent.read() { debug!("[upkeep] rent {} from {} tenants", event.total, event.tenants); }
+    for event in charged.read() { debug!("[upkeep] paid {}, {} buildings unpaid", event.paid, event.unpaid_buildings); }
+    for event in disrepair.read() { info!("[upkeep] {:?} {} fell into disrepair", event.item, event.building); }
+}
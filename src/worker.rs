@@ -1,6 +1,6 @@
-use bevy::{math::NormedVectorSpace, platform::collections::HashSet, prelude::*};
+use bevy::{ecs::system::SystemParam, math::NormedVectorSpace, platform::collections::HashSet, prelude::*};
 
-use crate::{buildings::{BuildingType, HouseData}, crop::PreparedDirtData, player::{CoinsSpawned, ItemType}, world::{Meridiem, WorldSettings}};
+use crate::{buildings::{BuildingCoords, BuildingMap, BuildingType, ConstructionSite, HouseData}, economy::WageContract, gamestate::GameState, jobs::{Job, JobBoard, JobCompleted, WorkerSkills}, grid::{world_to_tile, TerrainMap}, needs::WorkerNeeds, storage::Storage, pathfinding::{is_passable, walk_to, WorkerPath}, player::{ItemType, PlayerInventory}, world::WorldSettings};
 
 #[derive(Debug, Resource)]
 pub struct WorkerAmount { total: i32 }
@@ -8,31 +8,120 @@ pub struct WorkerAmount { total: i32 }
 #[derive(Debug, Component)]
 pub struct WorkerCollectable;
 
-#[derive(Component)]
-struct Employed;
+// coins lying around, not carried by a worker
+type CoinFilter = (With<WorkerCollectable>, Without<WorkerData>);
+// buildings that are built, not construction sites
+type BuiltFilter = (Without<WorkerData>, Without<ConstructionSite>);
+type BuiltHouseFilter = (With<HouseData>, BuiltFilter);
+
+// what the worker is doing right now, every state has its own system
+// change it only with `transition` so the WorkerStateChanged event is always sent
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkerState {
+    Unemployed, // looking around for a coin
+    SeekingPay, // walking to a coin
+    Idle, // hired, waiting for a job or the work time
+    Commuting, // walking to the job
+    Working, // at the job
+    Delivering, // bringing carried goods back
+    GoingHome,
+    Sleeping
+}
 
-#[derive(Component)]
-struct Working;
+impl WorkerState {
+    pub fn can_transition_to(&self, to: WorkerState) -> bool {
+        use WorkerState::*;
+
+        matches!((self, to),
+            (Unemployed, SeekingPay) |
+            (SeekingPay, Unemployed) | (SeekingPay, Idle) |
+            (Idle, Commuting) | (Idle, GoingHome) | (Idle, Delivering) |
+            (Commuting, Working) | (Commuting, GoingHome) | (Commuting, Idle) |
+            (Working, GoingHome) | (Working, Idle) |
+            (Delivering, Idle) |
+            (GoingHome, Sleeping) | (GoingHome, Idle) |
+            (Sleeping, Idle) |
+            // quitting
+            (Idle | Commuting | Working | Delivering | GoingHome | Sleeping, Unemployed)
+        )
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct WorkerStateChanged {
+    pub worker: Entity,
+    pub from: WorkerState,
+    pub to: WorkerState
+}
+
+// returns false and keeps the state if the transition is not allowed
+pub fn transition(worker: Entity, state: &mut WorkerState, to: WorkerState, changed: &mut EventWriter<WorkerStateChanged>) -> bool {
+    if !state.can_transition_to(to) {
+        warn!("[worker] {} can't go from {:?} to {:?}", worker, state, to);
+        return false;
+    }
+
+    changed.write(WorkerStateChanged { worker, from: *state, to });
+    *state = to;
+    true
+}
+
+// an entity the worker is going to and the tile where it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkTarget {
+    pub entity: Entity,
+    pub tile: (i32,i32)
+}
 
 #[derive(Component)]
 pub struct WorkerData {
     pub coins: i32,
     pub worker_speed: f32,
-    pub house_pos: (i32,i32),
-    pub house_assigned: bool,
-    pub target_crop_pos: Option<Vec3>,
-    pub target_crop_entity: Option<Entity>,
-    pub target_crop_active: bool, // is closer to the crop?
-    pub target_coin_pos: Option<Vec3>,
-    pub target_coin_dir: Option<Vec3>,
-    pub target_coin_entity: Option<Entity>,
+    pub house: Option<WorkTarget>,
+    pub coin: Option<WorkTarget>,
+    pub job: Option<Job>, // claimed from the JobBoard
+    pub job_progress: f32 // seconds worked on the job
+}
+
+// goods the worker picked up at a job and still has to deliver
+#[derive(Debug, Component, Default)]
+pub struct CarriedInventory {
+    pub items: Vec<(ItemType, i32)>,
+    pub destination: Option<WorkTarget> // hauled goods go to this building instead of a storage
+}
+
+impl CarriedInventory {
+    pub fn add(&mut self, item: ItemType, amount: i32) {
+        match self.items.iter_mut().find(|i| i.0 == item) {
+            Some(carried) => carried.1 += amount,
+            None => self.items.push((item, amount))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[derive(Debug, Event)]
+pub struct GoodsDelivered {
+    pub worker: Entity,
+    pub item: ItemType,
+    pub amount: i32,
+    pub storage: Option<Entity> // the storage or hauling destination, None when it went to the player inventory
 }
 
 #[derive(Bundle)]
 pub struct WorkerBundle {
     pub spr: Sprite,
     pub tf: Transform,
-    pub data: WorkerData
+    pub data: WorkerData,
+    pub path: WorkerPath,
+    pub state: WorkerState,
+    pub needs: WorkerNeeds,
+    pub contract: WageContract,
+    pub skills: WorkerSkills,
+    pub carried: CarriedInventory
 }
 
 pub struct MyWorkerPlugin;
@@ -40,12 +129,22 @@ pub struct MyWorkerPlugin;
 impl Plugin for MyWorkerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(WorkerAmount { total: 0 });
+        app.add_event::<WorkerStateChanged>();
+        app.add_event::<GoodsDelivered>();
         app.add_systems(Startup, setup);
         app.add_systems(Update, worker_amount_update);
-        // SYSTEMS []
-        app.add_systems(Update, (worker_collect_coin, worker_assign_home, worker_life_cycle));
-        // app.add_systems(Update, (wwww,wwwx,wwwy,wwwz));
-        // app.add_systems(Update, (wwwy,wwwz,worker_assign_home));
+        // SYSTEMS [] one per state
+        app.add_systems(Update, (
+            worker_unemployed,
+            worker_seeking_pay,
+            worker_idle,
+            worker_commuting,
+            worker_working,
+            worker_delivering,
+            worker_going_home,
+            worker_sleeping
+        ).chain().run_if(in_state(GameState::Playing)));
+        app.add_systems(Update, (log_worker_state_changes, log_goods_delivered));
     }
 }
 
@@ -57,18 +156,13 @@ fn setup(mut cmm: Commands) {
                 ..default()
             },
         tf: Transform::from_xyz(4.,4., 2.),
-        data: WorkerData { 
-            coins: 0,
-            worker_speed: 1.0,
-            target_crop_pos: Option::None,
-            target_crop_entity: Option::None,
-            target_crop_active: false,
-            target_coin_entity: Option::None, 
-            target_coin_pos: Option::None, 
-            target_coin_dir: Option::None, 
-            house_pos: (0,0), 
-            house_assigned: false 
-        }
+        data: WorkerData { coins: 0, worker_speed: 1.0, house: Option::None, coin: Option::None, job: Option::None, job_progress: 0. },
+        path: WorkerPath::default(),
+        state: WorkerState::Unemployed,
+        needs: WorkerNeeds::default(),
+        contract: WageContract::default(),
+        skills: WorkerSkills::default(),
+        carried: CarriedInventory::default()
     });
 }
 
@@ -81,194 +175,338 @@ fn worker_amount_update(
     workers.total = (houses * house_capacity) as i32; // change later when houses can be upgraded
 }
 
-fn worker_collect_coin(
-    time: Res<Time>, 
-    mut cmm: Commands,
-    mut coins_spawned: ResMut<CoinsSpawned>, // replace
-    coins_query: Query<(&Transform, Entity, &ItemType), (With<WorkerCollectable>, Without<WorkerData>)>,
-    mut workers: Query<(&mut Transform, &mut WorkerData, Entity), (With<WorkerData>, (Without<WorkerCollectable>, Without<Employed>))>, 
+pub fn is_work_time(day: &WorldSettings) -> bool {
+    (8..21).contains(&day.hour)
+}
+
+// base speed of the worker scaled by the biome under it and by how hungry and tired it is
+fn walk_speed(worker: &WorkerData, needs: &WorkerNeeds, position: Vec3, terrain: &TerrainMap) -> f32 {
+    worker.worker_speed * needs.work_speed() * terrain.biome_at(world_to_tile(position.truncate())).modifiers().worker_speed
+}
+
+// what the states that walk somewhere need
+#[derive(SystemParam)]
+struct Walking<'w> {
+    time: Res<'w, Time>,
+    terrain: Res<'w, TerrainMap>,
+    building_coords: Res<'w, BuildingCoords>
+}
+
+impl Walking<'_> {
+    // moves the worker one frame towards `goal`, true when it is standing on it
+    fn walk(&self, goal: (i32,i32), worker: &WorkerData, needs: &WorkerNeeds, tf: &mut Transform, path: &mut WorkerPath) -> bool {
+        let step = walk_speed(worker, needs, tf.translation, &self.terrain) * self.time.delta_secs();
+        walk_to(goal, tf, path, step, |tile| is_passable(&self.terrain, &self.building_coords, tile, goal))
+    }
+}
+
+fn worker_unemployed(
+    mut changed: EventWriter<WorkerStateChanged>,
+    coins_query: Query<(&Transform, Entity), CoinFilter>,
+    mut workers: Query<(&Transform, &mut WorkerData, &WorkerPath, &mut WorkerState, Entity)>
 ) {
     let worker_view_distance = 8.;
 
-    let mut coins_assigned: HashSet<Entity> = HashSet::new(); // coins assigned to a worker
-    let workers_iterator: Vec<_> = workers.iter_mut().collect(); 
+    // coins assigned to a worker
+    let mut coins_assigned: HashSet<Entity> = workers.iter().filter_map(|w| w.1.coin.map(|c| c.entity)).collect();
 
-    for (mut worker_tf, mut worker_data, worker_entity) in workers_iterator {
+    for (worker_tf, mut worker_data, worker_path, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::Unemployed { continue; }
 
         if let Some(coin_data) = coins_query.iter().find(|c| 
             (c.0.translation.x - worker_tf.translation.x).norm() < worker_view_distance &&
             (c.0.translation.y - worker_tf.translation.y).norm() < worker_view_distance && 
-            worker_data.coins < 1 && !coins_assigned.contains(&c.1)
+            !coins_assigned.contains(&c.1) &&
+            !worker_path.unreachable.contains(&world_to_tile(c.0.translation.truncate()))
         ) {
-            let dir = (worker_tf.translation - coin_data.0.translation).normalize_or_zero();
             coins_assigned.insert(coin_data.1);
-            worker_data.target_coin_dir = Some(dir);
-            worker_data.target_coin_entity = Some(coin_data.1);
-            worker_data.target_coin_pos = Some(coin_data.0.translation);
+            worker_data.coin = Some(WorkTarget { entity: coin_data.1, tile: world_to_tile(coin_data.0.translation.truncate()) });
+            transition(worker_entity, &mut state, WorkerState::SeekingPay, &mut changed);
         };
+    }
+}
 
-        if worker_data.target_coin_dir == Option::None || worker_data.target_coin_pos == Option::None || worker_data.target_coin_entity == Option::None { continue; }
-
-        let offset = 0.5;
-        let worker_pos = worker_data.target_coin_pos.unwrap();
-        let worker_dir = worker_data.target_coin_dir.unwrap();
-
-        let reset_worker_coin_data = | worker: &mut WorkerData | {
-            worker.target_coin_dir = Option::None;
-            worker.target_coin_pos = Option::None;
-            worker.target_coin_entity = Option::None;
+fn worker_seeking_pay(
+    time: Res<Time>, 
+    terrain: Res<TerrainMap>,
+    mut cmm: Commands,
+    mut changed: EventWriter<WorkerStateChanged>,
+    mut building_map: BuildingMap,
+    coins_query: Query<Entity, CoinFilter>,
+    mut workers: Query<(&mut Transform, &mut WorkerData, &WorkerNeeds, &mut WorkerPath, &mut WorkerState, Entity)>
+) {
+    for (mut worker_tf, mut worker_data, needs, mut worker_path, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::SeekingPay { continue; }
+
+        // if coin despawn then worker stops moving
+        let Some(coin) = worker_data.coin.filter(|c| coins_query.contains(c.entity)) else {
+            worker_data.coin = Option::None;
+            worker_path.clear();
+            transition(worker_entity, &mut state, WorkerState::Unemployed, &mut changed);
+            continue;
         };
 
-        if worker_tf.translation.x > (worker_pos.x - offset) && worker_tf.translation.x < (worker_pos.x + offset) {
+        let step = walk_speed(&worker_data, needs, worker_tf.translation, &terrain) * time.delta_secs();
+
+        if walk_to(coin.tile, &mut worker_tf, &mut worker_path, step, |tile| is_passable(&terrain, &building_map.coords, tile, coin.tile)) {
             worker_data.coins += 1;
-            cmm.entity(worker_data.target_coin_entity.unwrap()).despawn();
-            coins_spawned.positions.remove(&(worker_tf.translation.x as i32, worker_tf.translation.y as i32)); // not working properly
-            cmm.entity(worker_entity).insert(Employed);
-
-            coins_assigned.remove(&worker_data.target_coin_entity.unwrap());
-
-            reset_worker_coin_data(&mut worker_data);
-        }else { worker_tf.translation -= worker_dir * worker_data.worker_speed * time.delta_secs(); }
-        
-        if let Some(_) = coins_query.iter().find(|c|
-            worker_data.target_coin_entity != Option::None && c.1==worker_data.target_coin_entity.unwrap()
-        ) { }else { // if coin despawn then worker stops moving
-            reset_worker_coin_data(&mut worker_data);
+            cmm.entity(coin.entity).despawn();
+            building_map.remove(coin.tile, ItemType::Coin);
+
+            worker_data.coin = Option::None;
+            worker_path.clear();
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+        } else if worker_path.blocked {
+            // the coin is left for another worker
+            worker_data.coin = Option::None;
+            worker_path.give_up();
+            transition(worker_entity, &mut state, WorkerState::Unemployed, &mut changed);
         }
     }
 }
 
-fn worker_assign_home(
-    mut cmm: Commands,
-    mut houses: Query<(&mut HouseData, &Transform, Entity), (With<HouseData>, Without<Employed>)>,
-    mut workers_query: Query<(&mut Transform, &mut WorkerData, Entity), With<Employed>>
+// hired workers get a house, deliver what they carry and then claim the best job on the board
+fn worker_idle(
+    day: Res<WorldSettings>,
+    mut board: ResMut<JobBoard>,
+    mut changed: EventWriter<WorkerStateChanged>,
+    mut houses: Query<(&mut HouseData, &Transform, Entity), BuiltFilter>,
+    mut workers: Query<(&Transform, &mut WorkerData, &WorkerSkills, &CarriedInventory, &WorkerPath, &mut WorkerState, Entity)>
+) {
+    for (worker_tf, mut worker_data, skills, carried, worker_path, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::Idle { continue; }
+
+        if worker_data.house.is_none_or(|h| !houses.contains(h.entity)) {
+            worker_data.house = Option::None;
+            if let Some((mut house, house_tf, house_entity)) = houses.iter_mut().find(|house| house.0.assigned_workers.len() < house.0.max_capacity as usize) {
+                house.assigned_workers.insert(worker_entity);
+                worker_data.house = Some(WorkTarget { entity: house_entity, tile: world_to_tile(house_tf.translation.truncate()) });
+            }
+        }
+
+        if !carried.is_empty() {
+            transition(worker_entity, &mut state, WorkerState::Delivering, &mut changed);
+            continue;
+        }
+
+        if !is_work_time(&day) {
+            if worker_data.house.is_some() { transition(worker_entity, &mut state, WorkerState::GoingHome, &mut changed); }
+            continue;
+        }
+
+        if worker_data.job.is_none() {
+            worker_data.job = board.claim_best(worker_entity, world_to_tile(worker_tf.translation.truncate()), skills, |tile| !worker_path.unreachable.contains(&tile));
+        }
+
+        if worker_data.job.is_some() {
+            transition(worker_entity, &mut state, WorkerState::Commuting, &mut changed);
+        }
+    }
+}
+
+// gives the job back to the board, the worker drops it
+fn drop_job(worker_data: &mut WorkerData, board: &mut JobBoard) {
+    if let Some(job) = worker_data.job.take() { board.release(job.id); }
+    worker_data.job_progress = 0.;
+}
+
+fn worker_commuting(
+    day: Res<WorldSettings>,
+    walking: Walking,
+    mut board: ResMut<JobBoard>,
+    mut changed: EventWriter<WorkerStateChanged>,
+    mut workers: Query<(&mut Transform, &mut WorkerData, &WorkerNeeds, &mut WorkerPath, &mut WorkerState, Entity)>
 ) {
-    if let Some(mut available_house) = houses.iter_mut().find(|house| house.0.assigned_workers.len() < 1) {
-
-        let employes: Vec<_> = workers_query.iter_mut().collect();
-
-        for mut employed_worker in employes {
-            if employed_worker.1.coins == 1 && !employed_worker.1.house_assigned && available_house.0.assigned_workers.len() < 1 {
-                available_house.0.assigned_workers.insert(employed_worker.2);
-                employed_worker.1.house_pos = (available_house.1.translation.x as i32, available_house.1.translation.y as i32);
-                employed_worker.0.translation = Vec3::new(available_house.1.translation.x, available_house.1.translation.y, 2.);
-                employed_worker.1.house_assigned = true;
-                cmm.entity(employed_worker.2).insert(Working);
-                cmm.entity(employed_worker.2).remove::<Employed>();
-            } 
+    for (mut worker_tf, mut worker_data, needs, mut worker_path, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::Commuting { continue; }
+
+        // the job was removed from the board (target gone, crop ready...)
+        let Some(job) = worker_data.job.filter(|j| board.is_claimed_by(j.id, worker_entity)) else {
+            worker_data.job = Option::None;
+            worker_path.clear();
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+            continue;
+        };
+
+        if !is_work_time(&day) {
+            drop_job(&mut worker_data, &mut board);
+            transition(worker_entity, &mut state, WorkerState::GoingHome, &mut changed);
+            continue;
+        }
+
+        if walking.walk(job.tile, &worker_data, needs, &mut worker_tf, &mut worker_path) {
+            worker_data.job_progress = 0.;
+            transition(worker_entity, &mut state, WorkerState::Working, &mut changed);
+        } else if worker_path.blocked {
+            // another worker may reach it
+            drop_job(&mut worker_data, &mut board);
+            worker_path.give_up();
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
         }
     }
 }
 
-fn worker_life_cycle( // optimize this later
+// works at the job target until it is done, tending goes on until the board removes the job
+pub fn worker_working(
     time: Res<Time>,
     day: Res<WorldSettings>,
-    mut crops: Query<(&Transform, &mut PreparedDirtData, Entity), (With<PreparedDirtData>, Without<Working>)>,
-    mut worker: Query<(&mut Transform, &mut WorkerData, Entity), (With<Working>, Without<Employed>)>
+    mut board: ResMut<JobBoard>,
+    mut changed: EventWriter<WorkerStateChanged>,
+    mut completed: EventWriter<JobCompleted>,
+    mut workers: Query<(&mut WorkerData, &WorkerNeeds, &mut WorkerState, Entity)>
 ) {
-    for mut w in worker.iter_mut() {
-        
-        // assign
-        if let Some(mut crop) = crops.iter_mut().find(|c|!c.1.worker_assigned_bool && w.1.target_crop_pos == Option::None && c.1.crop_type_selected) {
-            crop.1.worker_assigned_bool = true;
-            crop.1.worker_assigned_entity = w.2;
-            w.1.target_crop_pos = Some(crop.0.translation);
-            w.1.target_crop_entity = Some(crop.2);
+    for (mut worker_data, needs, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::Working { continue; }
+
+        let Some(job) = worker_data.job.filter(|j| board.is_claimed_by(j.id, worker_entity)) else {
+            worker_data.job = Option::None;
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+            continue;
         };
 
-        //move
-        if w.1.target_crop_pos != Option::None {
-            let is_work_time =
-                (day.meridiem == Meridiem::AM && day.actual_hour >= 8.0) ||
-                (day.meridiem == Meridiem::PM && day.actual_hour < 9.0);
+        if !is_work_time(&day) {
+            drop_job(&mut worker_data, &mut board);
+            transition(worker_entity, &mut state, WorkerState::GoingHome, &mut changed);
+            continue;
+        }
 
-            let crop_entity = crops.iter_mut().find(|c|c.2 == w.1.target_crop_entity.unwrap());
+        if job.kind.is_continuous() { continue; }
 
-            if is_work_time {
-                let dir = (w.0.translation - w.1.target_crop_pos.unwrap()).normalize_or_zero();
+        worker_data.job_progress += time.delta_secs() * needs.work_speed();
 
-                // if w.0.translation.x < (w.1.target_crop_pos.unwrap().x - 5.) || w.0.translation.x > (w.1.target_crop_pos.unwrap().x + 5.){
-                //     w.0.translation -= dir * w.1.worker_speed * time.delta_secs();
-                // }
+        if worker_data.job_progress >= job.kind.work_seconds() {
+            board.complete(job.id);
+            completed.write(JobCompleted { job, worker: worker_entity });
+            worker_data.job = Option::None;
+            worker_data.job_progress = 0.;
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+        }
+    }
+}
 
-                w.0.translation -= dir * w.1.worker_speed * time.delta_secs();
+// brings the carried goods to the hauling destination or the nearest storage with space
+// without one they go to the worker house and into the player inventory,
+// without a house they are handed over where the worker stands
+// unreachable destinations are given up and the next one is tried
+fn worker_delivering(
+    walking: Walking,
+    mut inventory: ResMut<PlayerInventory>,
+    mut changed: EventWriter<WorkerStateChanged>,
+    mut delivered: EventWriter<GoodsDelivered>,
+    mut storages: Query<(&mut Storage, &Transform, Entity), BuiltFilter>,
+    built: Query<(), BuiltFilter>,
+    mut workers: Query<(&mut Transform, &WorkerData, &WorkerNeeds, &mut CarriedInventory, &mut WorkerPath, &mut WorkerState, Entity)>
+) {
+    for (mut worker_tf, worker_data, needs, mut carried, mut worker_path, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::Delivering { continue; }
+
+        // the destination module takes the goods from the GoodsDelivered events
+        if let Some(destination) = carried.destination.filter(|d| built.contains(d.entity)) {
+            if !walking.walk(destination.tile, worker_data, needs, &mut worker_tf, &mut worker_path) {
+                if worker_path.blocked {
+                    worker_path.give_up();
+                    carried.destination = Option::None;
+                }
+                continue;
+            }
 
-                if dir.x < 1.0 && dir.y < 1.0 { crop_entity.unwrap().1.growth_active = true; continue; } // fix this later
-                crop_entity.unwrap().1.growth_active = false;
-            } else {
-                crop_entity.unwrap().1.growth_active = false;
-                let dir = w.0.translation - Vec3::new(w.1.house_pos.0 as f32,w.1.house_pos.1 as f32,2.);
-                w.0.translation -= dir * w.1.worker_speed * time.delta_secs();
+            for (item, amount) in carried.items.drain(..) {
+                delivered.write(GoodsDelivered { worker: worker_entity, item, amount, storage: Some(destination.entity) });
             }
+            carried.destination = Option::None;
+            worker_path.clear();
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+            continue;
+        }
+        // the destination is gone, the goods go to a storage like any other
+        if carried.destination.take().is_some() { worker_path.clear(); }
+
+        let position = worker_tf.translation.truncate();
+        let storage = storages.iter()
+            .filter(|(storage, tf, _)| storage.space() > 0 && !worker_path.unreachable.contains(&world_to_tile(tf.translation.truncate())))
+            .min_by(|a, b| a.1.translation.truncate().distance_squared(position).total_cmp(&b.1.translation.truncate().distance_squared(position)))
+            .map(|(_, tf, entity)| WorkTarget { entity, tile: world_to_tile(tf.translation.truncate()) });
+        let house = worker_data.house.filter(|h| built.contains(h.entity) && !worker_path.unreachable.contains(&h.tile));
+
+        if let Some(target) = storage.or(house)
+            && !walking.walk(target.tile, worker_data, needs, &mut worker_tf, &mut worker_path) {
+            // the next storage, then the house, is tried on the next frame
+            if worker_path.blocked { worker_path.give_up(); }
+            continue;
+        }
+
+        // whatever doesn't fit stays carried, the worker looks for another storage or waits for room
+        let mut storage = storage.and_then(|s| storages.get_mut(s.entity).ok());
+        carried.items.retain_mut(|(item, amount)| {
+            let left = match storage.as_mut() {
+                Some((storage, _, _)) => storage.add(*item, *amount),
+                None => inventory.add(*item, *amount)
+            };
+            if left < *amount {
+                delivered.write(GoodsDelivered { worker: worker_entity, item: *item, amount: *amount - left, storage: storage.as_ref().map(|s| s.2) });
+            }
+            *amount = left;
+            left > 0
+        });
+
+        if carried.is_empty() {
+            worker_path.clear();
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
         }
     }
 }
 
-// // all this part neds to be rewrited
-// fn wwwz(mut workers: Query<(&mut Transform, &mut WorkerData,), (With<WorkerData>, Without<WorkerCollectable>)>, coins: Query<(&Transform, Entity, &ItemType), (With<WorkerCollectable>, Without<WorkerData>)>) {
-//     let distance: f32 = 8.;
-
-//     for (tf, mut data) in &mut workers {
-
-//         if let Some(coin) = coins.iter().find(|c| (c.0.translation.x - tf.translation.x).norm() < distance && (c.0.translation.y - tf.translation.y).norm() < distance && data.coins < 1) {
-//             let dir = (tf.translation - coin.0.translation).normalize_or_zero();
-//             data.target_coin_dir = Some(dir);
-//             data.target_coin_pos = Some(coin.0.translation);
-//             data.target_coin_entity = Some(coin.1);
-//         }
-//     }
-// }
-
-// fn wwwy(time: Res<Time>, mut cmm: Commands, mut coins_spawned: ResMut<CoinsSpawned>, mut workers: Query<(&mut Transform, &mut WorkerData, Entity), (With<WorkerData>, Without<WorkerCollectable>)>, coins: Query<Entity, (With<WorkerCollectable>, Without<WorkerData>)>) {
-//     for (mut worker_tf,mut worker_data, worker_entity) in &mut workers {
-//         if worker_data.target_coin_dir == Option::None || worker_data.target_coin_pos == Option::None || worker_data.target_coin_entity == Option::None { continue; }
-
-//         let speed = 1.0;
-//         let offset = 0.1;
-//         let worker_pos = worker_data.target_coin_pos.unwrap();
-//         let worker_dir = worker_data.target_coin_dir.unwrap();
-
-//         let reset_worker_coin_data = | worker: &mut WorkerData | {
-//             worker.target_coin_dir = Option::None;
-//             worker.target_coin_pos = Option::None;
-//             worker.target_coin_entity = Option::None;
-//         };
-
-//         if worker_tf.translation.x > (worker_pos.x - offset) && worker_tf.translation.x < (worker_pos.x + offset) {
-//             worker_data.coins += 1;
-//             println!(" collected a coin ");
-//             cmm.entity(worker_data.target_coin_entity.unwrap()).despawn();
-//             coins_spawned.positions.remove(&(worker_tf.translation.x as i32, worker_tf.translation.y as i32)); // not working properly
-//             cmm.entity(worker_entity).insert(Employed);
-//             reset_worker_coin_data(&mut worker_data);
-//         }else { worker_tf.translation -= worker_dir * speed * time.delta_secs(); }
-        
-//         if let Some(_) = coins.iter().find(|c|worker_data.target_coin_entity != Option::None && *c == worker_data.target_coin_entity.unwrap()) { }else { // if coin despawn then worker stops moving
-//             reset_worker_coin_data(&mut worker_data);
-//         }
-//     }
-// }
-
-// // assign it to an available home
-// fn wwwx(mut houses: Query<(&mut HouseData, &Transform)>, mut workers: Query<(&Transform, &mut WorkerData, Entity), (With<Employed>, Without<WorkerCollectable>)>) {
-//     if let Some(mut house) = houses.iter_mut().find(|(h, _)| h.building_type == BuildingType::House && h.assigned_workers.len() < h.max_capacity as usize ) {
-//         if let Some(mut worker) = workers.iter_mut().find(|w|w.1.coins == 1 && w.1.house_pos == (0,0)) {
-//             worker.1.house_pos = (house.1.translation.x as i32,house.1.translation.y as i32);
-//             house.0.assigned_workers.insert(worker.2);
-//             println!("assigned: {:?}", worker.2);
-//         }
-//     } else { return; }
-// }
-
-// // the worker walks to his house
-// fn wwww(time: Res<Time>, mut workers: Query<(&mut Transform, &WorkerData, Entity), (With<WorkerData>, Without<WorkerCollectable>)>) {
-
-//     for mut w in &mut workers { // change it to filter thern iter
-//         if w.1.coins == 1 && w.1.house_pos != (0,0) {
-//             let dir = w.0.translation - Vec3::new(w.1.house_pos.0 as f32,w.1.house_pos.1 as f32, 1.);
-//             w.0.translation -= dir * time.delta_secs();
-//         }
-//     }
-// }
-
-// // working cicle
+fn worker_going_home(
+    day: Res<WorldSettings>,
+    walking: Walking,
+    mut changed: EventWriter<WorkerStateChanged>,
+    houses: Query<Entity, BuiltHouseFilter>,
+    mut workers: Query<(&mut Transform, &WorkerData, &WorkerNeeds, &mut WorkerPath, &mut WorkerState, Entity)>
+) {
+    for (mut worker_tf, worker_data, needs, mut worker_path, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::GoingHome { continue; }
+
+        let house = worker_data.house.filter(|h| houses.contains(h.entity));
+
+        if house.is_none() || is_work_time(&day) {
+            worker_path.clear();
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+            continue;
+        }
+
+        let Some(house) = house else { continue; };
+
+        if walking.walk(house.tile, worker_data, needs, &mut worker_tf, &mut worker_path) {
+            transition(worker_entity, &mut state, WorkerState::Sleeping, &mut changed);
+        }
+    }
+}
+
+fn worker_sleeping(
+    day: Res<WorldSettings>,
+    mut changed: EventWriter<WorkerStateChanged>,
+    houses: Query<Entity, BuiltHouseFilter>,
+    mut workers: Query<(&WorkerData, &mut WorkerState, Entity)>
+) {
+    for (worker_data, mut state, worker_entity) in &mut workers {
+        if *state != WorkerState::Sleeping { continue; }
+
+        let house_exists = worker_data.house.is_some_and(|h| houses.contains(h.entity));
+
+        if is_work_time(&day) || !house_exists {
+            transition(worker_entity, &mut state, WorkerState::Idle, &mut changed);
+        }
+    }
+}
+
+fn log_worker_state_changes(mut changed: EventReader<WorkerStateChanged>) {
+    for change in changed.read() {
+        debug!("[worker] {} {:?} -> {:?}", change.worker, change.from, change.to);
+    }
+}
+
+fn log_goods_delivered(mut delivered: EventReader<GoodsDelivered>) {
+    for event in delivered.read() {
+        debug!("[worker] {} delivered {} {:?}", event.worker, event.amount, event.item);
+    }
+}
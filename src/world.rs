@@ -1,51 +1,116 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::{buildings::HouseData, worker::WorkerData};
+use crate::{buildings::HouseData, gamestate::GameState, worker::WorkerData};
 
 const HOUR: f32 = 2.0;
 
-#[derive(Debug, Component, PartialEq, Eq)]
-pub enum Meridiem {
-    AM,
-    PM
+const HOURS_PER_DAY: u32 = 24;
+pub const DAYS_PER_SEASON: u32 = 7;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter
+}
+
+impl Season {
+    pub fn next(&self) -> Season {
+        match self {
+            Season::Spring => Season::Summer,
+            Season::Summer => Season::Autumn,
+            Season::Autumn => Season::Winter,
+            Season::Winter => Season::Spring
+        }
+    }
 }
 
+// the calendar, every HOUR real seconds the clock moves one in-game hour
 #[derive(Debug, Resource)]
 pub struct WorldSettings {
     day_timer: Timer,
-    pub actual_hour: f32,
-    pub meridiem: Meridiem
+    pub hour: u32, // 0..24
+    pub day: u32, // day of the season, starts at 1
+    pub season: Season,
+    pub year: u32 // starts at 1
+}
+
+impl WorldSettings {
+    // hour with the fraction that already passed, 0.0..24.0
+    pub fn time_of_day(&self) -> f32 {
+        self.hour as f32 + self.day_timer.fraction()
+    }
+}
+
+// sent every time the clock moves to the next in-game hour
+#[derive(Debug, Event)]
+pub struct HourChanged {
+    pub hour: u32
+}
+
+// sent at midnight, before the HourChanged of hour 0
+#[derive(Debug, Event)]
+pub struct DayStarted {
+    pub day: u32,
+    pub season: Season,
+    pub year: u32
+}
+
+#[derive(Debug, Event)]
+pub struct SeasonChanged {
+    pub season: Season,
+    pub year: u32
 }
 
 pub struct MyWorldPlugin;
 
 impl Plugin for MyWorldPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(WorldSettings { day_timer: Timer::from_seconds(HOUR, TimerMode::Repeating), actual_hour: 0.0,  meridiem: Meridiem::AM });
+        app.insert_resource(WorldSettings { day_timer: Timer::from_seconds(HOUR, TimerMode::Repeating), hour: 0, day: 1, season: Season::Spring, year: 1 });
+        app.add_event::<HourChanged>();
+        app.add_event::<DayStarted>();
+        app.add_event::<SeasonChanged>();
    
-        app.add_systems(Update, day_timer_tick);
+        app.add_systems(Update, (day_timer_tick, log_calendar_events).chain().run_if(in_state(GameState::Playing)));
         app.add_systems(Update, clean_scene);
 
     }
 }
 
-fn day_timer_tick(time: Res<Time>, mut day: ResMut<WorldSettings>) {
+fn day_timer_tick(
+    time: Res<Time>,
+    mut day: ResMut<WorldSettings>,
+    mut hour_changed: EventWriter<HourChanged>,
+    mut day_started: EventWriter<DayStarted>,
+    mut season_changed: EventWriter<SeasonChanged>
+) {
     day.day_timer.tick(time.delta());
 
-    if day.day_timer.just_finished() { 
-        day.actual_hour += 1.0;
-        if day.actual_hour > 12.0 {
-            day.actual_hour = 1.0;
-            
-            day.meridiem = match day.meridiem {
-                Meridiem::AM => { Meridiem::PM },
-                Meridiem::PM => { Meridiem::AM }
+    for _ in 0..day.day_timer.times_finished_this_tick() {
+        day.hour = (day.hour + 1) % HOURS_PER_DAY;
+
+        if day.hour == 0 {
+            day.day += 1;
+            if day.day > DAYS_PER_SEASON {
+                day.day = 1;
+                day.season = day.season.next();
+                if day.season == Season::Spring { day.year += 1; }
+                season_changed.write(SeasonChanged { season: day.season, year: day.year });
             }
+            day_started.write(DayStarted { day: day.day, season: day.season, year: day.year });
         }
-        // println!("{:?} {:?}", day.actual_hour, day.meridiem);
+
+        hour_changed.write(HourChanged { hour: day.hour });
     }
 }
 
+fn log_calendar_events(mut day_started: EventReader<DayStarted>, mut season_changed: EventReader<SeasonChanged>) {
+    for event in season_changed.read() { info!("[calendar] {:?} of year {} begins", event.season, event.year); }
+    for event in day_started.read() { debug!("[calendar] day {} of {:?}, year {}", event.day, event.season, event.year); }
+}
+
 fn clean_scene(mut cmm: Commands, input: Res<ButtonInput<KeyCode>>, w: Query<Entity, With<WorkerData>>, h: Query<Entity, With<HouseData>>) {
     if input.just_pressed(KeyCode::KeyR) {
         for worker in w {